@@ -1,4 +1,44 @@
-export interface IPluginOptions {
+export interface IDialect {
+  /**
+   * Field delimiter, a single ASCII character. `\\t` and `tab` are accepted for a tab.
+   *
+   * @default ',' for `.csv`, '\t' for `.tsv`
+   */
+  delimiter?: string;
+
+  /**
+   * Quote character.
+   *
+   * @default '"'
+   */
+  quote?: string;
+
+  /**
+   * Escape character used inside quoted fields. When unset, quotes are escaped by doubling them.
+   */
+  escape?: string;
+
+  /**
+   * Lines starting with this character are ignored.
+   */
+  comment?: string;
+
+  /**
+   * Whitespace trimming of headers and/or fields.
+   *
+   * @default 'none'
+   */
+  trim?: 'none' | 'headers' | 'fields' | 'all';
+
+  /**
+   * Allows rows with a different number of fields than the header.
+   *
+   * @default false
+   */
+  flexible?: boolean;
+}
+
+export interface IPluginOptions extends IDialect {
   /**
    * Specifies an array of regex patterns to include files in the plugin process.
    * Each string in the array should be a valid regular expression used to match file paths.
@@ -20,4 +60,60 @@ export interface IPluginOptions {
    * @type {string[]}
    */
   exclude?: string[];
+
+  /**
+   * Treats the first row as a header and exports every row as an object keyed by it.
+   * Set to `false` to export every row, including the first one, as an array of fields.
+   *
+   * @default true
+   */
+  headers?: boolean;
+
+  /**
+   * Converts numbers, booleans (`true`/`false`), empty fields (`null`) and ISO 8601 dates
+   * (`Date`) instead of exporting every field as a string. In spreadsheets and NDJSON files,
+   * where values are already typed, only text is inferred, to recognize dates.
+   *
+   * @default false
+   */
+  inferTypes?: boolean;
+
+  /**
+   * Extra extensions to handle, or overrides of the built-in `csv` and `tsv` dialects.
+   * Each entry is merged over the top level dialect options.
+   *
+   * @example
+   *  { psv: { delimiter: '|' }, dsv: { delimiter: ';', trim: 'all' } }
+   */
+  extensions?: Record<string, IDialect>;
+
+  /**
+   * Fails the build on malformed rows. When `false`, malformed rows are skipped and
+   * the number of skipped rows is reported as a warning.
+   *
+   * @default true
+   */
+  strict?: boolean;
+
+  /**
+   * Generates TypeScript declarations typing the rows from the header names and, with
+   * `inferTypes`, the inferred column types. The declarations are regenerated whenever a file changes.
+   * - `true`: writes `<name>.d.<ext>.ts` next to every file, e.g. `fruit.d.csv.ts`.
+   *   This requires `allowArbitraryExtensions` in `tsconfig.json`.
+   * - `string`: writes one declaration file at this path, relative to the project root,
+   *   declaring a `*\/<file name>` module for every file, prefixed by its directories when file names collide.
+   *
+   * @default false
+   */
+  dts?: boolean | string;
+
+  /**
+   * Also exports every column as a named export, e.g. `export const price = [...]`,
+   * so bundles only keep the columns they import. Headers which are not valid identifiers
+   * are exported as string names (`import { "unit price" as unitPrice } from './data.csv'`).
+   * Requires `headers`.
+   *
+   * @default false
+   */
+  namedExports?: boolean;
 }
@@ -0,0 +1,196 @@
+use farmfe_core::{config::asset::AssetFormatMode, serde_json, swc_ecma_ast::Ident};
+
+use crate::{infer::Cell, table::Table};
+
+/// Whether `name` can be used as is in `export const {name} = ...`, checked by
+/// swc so reserved words such as `public` stay in sync with the parser.
+pub fn is_valid_identifier(name: &str) -> bool {
+  Ident::verify_symbol(name).is_ok()
+}
+
+/// A column or a sheet exported by name, `local` differs from `name` when the
+/// name is not a valid identifier and has to be exported as
+/// `export { local as "name" }`.
+pub struct NamedExport {
+  pub name: String,
+  pub local: String,
+  pub index: usize,
+}
+
+/// One export per distinct name, locals of invalid identifiers being
+/// `{local_prefix}{index}`. Like the row objects, where the last of several
+/// equal keys wins, a duplicated name exports its last index. `default` is
+/// skipped as it would clash with the default export.
+fn named_exports(names: &[String], local_prefix: &str) -> Vec<NamedExport> {
+  let mut exports: Vec<NamedExport> = vec![];
+  for (index, name) in names.iter().enumerate() {
+    if name == "default" {
+      continue;
+    }
+    if let Some(export) = exports.iter_mut().find(|export| &export.name == name) {
+      export.index = index;
+      continue;
+    }
+    let local = if is_valid_identifier(name) {
+      name.clone()
+    } else {
+      format!("{local_prefix}{index}")
+    };
+    exports.push(NamedExport {
+      name: name.clone(),
+      local,
+      index,
+    });
+  }
+  exports
+}
+
+pub fn column_exports(headers: &[String]) -> Vec<NamedExport> {
+  named_exports(headers, "__dsv_column_")
+}
+
+pub fn sheet_exports(sheets: &[(String, Table)]) -> Vec<NamedExport> {
+  let names = sheets
+    .iter()
+    .map(|(name, _)| name.clone())
+    .collect::<Vec<_>>();
+  named_exports(&names, "__dsv_sheet_")
+}
+
+fn stringify_named_export(export: &NamedExport, value: &str) -> String {
+  if export.local == export.name {
+    format!("export const {} = {value};\n", export.local)
+  } else {
+    format!(
+      "const {} = {value};\nexport {{ {} as {} }};\n",
+      export.local,
+      export.local,
+      serde_json::to_string(&export.name).unwrap()
+    )
+  }
+}
+
+pub fn stringify_rows(headers: Option<&[String]>, rows: &[Vec<Cell>]) -> String {
+  let rows = rows
+    .iter()
+    .map(|row| match headers {
+      Some(headers) => {
+        let fields = headers
+          .iter()
+          .zip(row)
+          .map(|(key, cell)| format!("{}:{}", serde_json::to_string(key).unwrap(), cell.to_js()))
+          .collect::<Vec<_>>();
+        format!("{{{}}}", fields.join(","))
+      }
+      None => {
+        let fields = row.iter().map(Cell::to_js).collect::<Vec<_>>();
+        format!("[{}]", fields.join(","))
+      }
+    })
+    .collect::<Vec<_>>();
+  format!("[{}]", rows.join(","))
+}
+
+/// `export const {column} = [...]` for every column, so a bundle only keeps
+/// the columns that are actually imported.
+pub fn stringify_column_exports(headers: &[String], rows: &[Vec<Cell>]) -> String {
+  column_exports(headers)
+    .iter()
+    .map(|export| {
+      let values = rows
+        .iter()
+        .map(|row| {
+          row
+            .get(export.index)
+            .map_or("null".to_string(), Cell::to_js)
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+      stringify_named_export(export, &format!("[{values}]"))
+    })
+    .collect()
+}
+
+/// Module of a workbook imported without `?sheet`: every sheet is exported by
+/// its name and the first one is also the default export.
+pub fn stringify_workbook(sheets: &[(String, Table)]) -> String {
+  let mut code = String::new();
+  let exports = sheet_exports(sheets);
+  for export in &exports {
+    let (_, table) = &sheets[export.index];
+    code.push_str(&stringify_named_export(
+      export,
+      &stringify_rows(table.headers.as_deref(), &table.rows),
+    ));
+  }
+  let default = match exports.iter().find(|export| export.index == 0) {
+    Some(export) => export.local.clone(),
+    None => sheets.first().map_or("[]".to_string(), |(_, table)| {
+      stringify_rows(table.headers.as_deref(), &table.rows)
+    }),
+  };
+  code.push_str(&format!("export default {default};\n"));
+  code
+}
+
+/// Module exporting a loader of the rows emitted as the JSON asset `url`. The
+/// promise is cached so the asset is only fetched once.
+pub fn stringify_lazy_loader(url: &str, mode: AssetFormatMode) -> String {
+  let url = serde_json::to_string(url).unwrap();
+  let load = match mode {
+    AssetFormatMode::Browser => format!("fetch({url}).then((res) => res.json())"),
+    AssetFormatMode::Node => format!(
+      "import('node:fs/promises').then((fs) => fs.readFile(new URL({url}, import.meta.url), 'utf-8')).then(JSON.parse)"
+    ),
+  };
+  format!("let data;\nexport default function load() {{\n  return data || (data = {load});\n}}\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::infer::infer_cell;
+
+  #[test]
+  fn test_stringify_column_exports() {
+    let headers = ["id", "unit price", "class", "id"].map(String::from);
+    let rows = vec![
+      ["1", "2.5", "a", "x"]
+        .map(|field| infer_cell(field, true))
+        .to_vec(),
+      vec![infer_cell("2", true)],
+    ];
+    assert_eq!(
+      stringify_column_exports(&headers, &rows),
+      "export const id = [\"x\",null];\n\
+       const __dsv_column_1 = [2.5,null];\nexport { __dsv_column_1 as \"unit price\" };\n\
+       const __dsv_column_2 = [\"a\",null];\nexport { __dsv_column_2 as \"class\" };\n"
+    );
+  }
+
+  #[test]
+  fn test_is_valid_identifier() {
+    assert!(is_valid_identifier("unit_price"));
+    assert!(!is_valid_identifier("with"));
+    assert!(!is_valid_identifier("public"));
+    assert!(!is_valid_identifier("2024"));
+  }
+
+  #[test]
+  fn test_stringify_workbook() {
+    let table = |cell: &str| Table {
+      headers: None,
+      rows: vec![vec![infer_cell(cell, false)]],
+    };
+    let sheets = vec![
+      ("Q1 2024".to_string(), table("a")),
+      ("summary".to_string(), table("b")),
+    ];
+    assert_eq!(
+      stringify_workbook(&sheets),
+      "const __dsv_sheet_0 = [[\"a\"]];\nexport { __dsv_sheet_0 as \"Q1 2024\" };\n\
+       export const summary = [[\"b\"]];\n\
+       export default __dsv_sheet_0;\n"
+    );
+  }
+}
@@ -0,0 +1,190 @@
+use std::{collections::HashMap, error::Error};
+
+use csv::{ReaderBuilder, Trim};
+use percent_encoding::percent_decode_str;
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TrimMode {
+  None,
+  Headers,
+  Fields,
+  All,
+}
+
+impl From<TrimMode> for Trim {
+  fn from(mode: TrimMode) -> Self {
+    match mode {
+      TrimMode::None => Trim::None,
+      TrimMode::Headers => Trim::Headers,
+      TrimMode::Fields => Trim::Fields,
+      TrimMode::All => Trim::All,
+    }
+  }
+}
+
+/// How a delimiter-separated file is split into rows and fields. Every field is
+/// optional so dialects can be layered: built-in extension defaults, plugin
+/// options, per-extension options and finally the query of the import.
+#[derive(serde::Deserialize, Clone, Default, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Dialect {
+  pub delimiter: Option<String>,
+  pub quote: Option<String>,
+  pub escape: Option<String>,
+  pub comment: Option<String>,
+  pub trim: Option<TrimMode>,
+  pub flexible: Option<bool>,
+}
+
+impl Dialect {
+  /// The dialect of the extensions supported without any configuration.
+  pub fn builtin(extname: &str) -> Option<Self> {
+    let delimiter = match extname {
+      "csv" => ",",
+      "tsv" => "\t",
+      _ => return None,
+    };
+    Some(Self {
+      delimiter: Some(delimiter.to_string()),
+      ..Default::default()
+    })
+  }
+
+  /// Returns a dialect where the fields set in `other` take precedence.
+  pub fn merge(&self, other: &Dialect) -> Self {
+    Self {
+      delimiter: other.delimiter.clone().or(self.delimiter.clone()),
+      quote: other.quote.clone().or(self.quote.clone()),
+      escape: other.escape.clone().or(self.escape.clone()),
+      comment: other.comment.clone().or(self.comment.clone()),
+      trim: other.trim.or(self.trim),
+      flexible: other.flexible.or(self.flexible),
+    }
+  }
+
+  /// Reads dialect overrides from an import query such as `?delimiter=;&trim=all`.
+  /// Values are percent-decoded, so `?delimiter=%09` reads tabs.
+  pub fn from_query(query: &[(String, String)]) -> Result<Self, Box<dyn Error>> {
+    let query = query
+      .iter()
+      .map(|(key, value)| {
+        Ok((
+          key.clone(),
+          percent_decode_str(value).decode_utf8()?.into_owned(),
+        ))
+      })
+      .collect::<Result<HashMap<_, _>, Box<dyn Error>>>()?;
+    let trim = match query.get("trim").map(String::as_str) {
+      Some("none") => Some(TrimMode::None),
+      Some("headers") => Some(TrimMode::Headers),
+      Some("fields") => Some(TrimMode::Fields),
+      Some("all") | Some("") => Some(TrimMode::All),
+      Some(value) => return Err(format!("Invalid `trim` query value `{value}`").into()),
+      None => None,
+    };
+    let flexible = match query.get("flexible").map(String::as_str) {
+      Some("true") | Some("") => Some(true),
+      Some("false") => Some(false),
+      Some(value) => return Err(format!("Invalid `flexible` query value `{value}`").into()),
+      None => None,
+    };
+    Ok(Self {
+      delimiter: query.get("delimiter").cloned(),
+      quote: query.get("quote").cloned(),
+      escape: query.get("escape").cloned(),
+      comment: query.get("comment").cloned(),
+      trim,
+      flexible,
+    })
+  }
+
+  pub fn reader_builder(&self) -> Result<ReaderBuilder, Box<dyn Error>> {
+    let mut builder = ReaderBuilder::new();
+    if let Some(delimiter) = &self.delimiter {
+      builder.delimiter(parse_byte("delimiter", delimiter)?);
+    }
+    if let Some(quote) = &self.quote {
+      builder.quote(parse_byte("quote", quote)?);
+    }
+    if let Some(escape) = &self.escape {
+      builder.escape(Some(parse_byte("escape", escape)?));
+    }
+    if let Some(comment) = &self.comment {
+      builder.comment(Some(parse_byte("comment", comment)?));
+    }
+    if let Some(trim) = self.trim {
+      builder.trim(trim.into());
+    }
+    if let Some(flexible) = self.flexible {
+      builder.flexible(flexible);
+    }
+    Ok(builder)
+  }
+}
+
+/// The csv reader works on single bytes, so every dialect character has to be
+/// one ASCII character. `\t` and `tab` are accepted since a literal tab is
+/// awkward to write in a config file or an import query.
+fn parse_byte(name: &str, value: &str) -> Result<u8, Box<dyn Error>> {
+  match value {
+    "\\t" | "tab" => Ok(b'\t'),
+    _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+    _ => Err(format!("`{name}` must be a single ASCII character, got `{value}`").into()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_merge_dialects() {
+    let options = Dialect {
+      delimiter: Some(";".to_string()),
+      trim: Some(TrimMode::All),
+      ..Default::default()
+    };
+    let query = Dialect::from_query(&[("delimiter".to_string(), "|".to_string())]).unwrap();
+    let dialect = Dialect::builtin("csv")
+      .unwrap()
+      .merge(&options)
+      .merge(&query);
+    assert_eq!(dialect.delimiter.as_deref(), Some("|"));
+    assert_eq!(dialect.trim, Some(TrimMode::All));
+
+    let mut reader = dialect
+      .reader_builder()
+      .unwrap()
+      .from_reader("a | b\n1 | 2".as_bytes());
+    let record = reader.records().next().unwrap().unwrap();
+    assert_eq!(record.iter().collect::<Vec<_>>(), vec!["1", "2"]);
+  }
+
+  #[test]
+  fn test_encoded_query() {
+    let query = |value: &str| {
+      Dialect::from_query(&[("delimiter".to_string(), value.to_string())])
+        .unwrap()
+        .delimiter
+    };
+    assert_eq!(query("%09").as_deref(), Some("\t"));
+    assert_eq!(query("%3B").as_deref(), Some(";"));
+    let mut reader = Dialect::from_query(&[("delimiter".to_string(), "%3B".to_string())])
+      .unwrap()
+      .reader_builder()
+      .unwrap()
+      .from_reader("a;b\n1;2\n".as_bytes());
+    assert_eq!(reader.headers().unwrap(), vec!["a", "b"]);
+  }
+
+  #[test]
+  fn test_invalid_dialect() {
+    let dialect = Dialect {
+      delimiter: Some("::".to_string()),
+      ..Default::default()
+    };
+    assert!(dialect.reader_builder().is_err());
+    assert!(Dialect::from_query(&[("trim".to_string(), "left".to_string())]).is_err());
+  }
+}
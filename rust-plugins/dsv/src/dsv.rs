@@ -0,0 +1,100 @@
+use std::{error::Error, path::Path};
+
+use farmfe_core::error::CompilationError;
+
+use crate::{
+  dialect::Dialect, error::csv_error_to_compilation_error, infer::infer_cell, table::Table, Options,
+};
+
+/// Resolves the dialect of a file, from the lowest to the highest precedence:
+/// the built-in dialect of its extension, the plugin options, the `extensions`
+/// entry of its extension and the query of the import. Returns `None` when the
+/// extension is neither built-in nor configured.
+pub fn get_dialect(
+  resolved_path: &str,
+  query: &[(String, String)],
+  options: &Options,
+) -> Result<Option<Dialect>, Box<dyn Error>> {
+  let Some(extname) = Path::new(resolved_path)
+    .extension()
+    .and_then(|ext| ext.to_str())
+  else {
+    return Ok(None);
+  };
+
+  let configured = options.extensions.as_ref().and_then(|extensions| {
+    extensions
+      .iter()
+      .find(|(ext, _)| ext.trim_start_matches('.') == extname)
+      .map(|(_, dialect)| dialect)
+  });
+  let builtin = Dialect::builtin(extname);
+  if builtin.is_none() && configured.is_none() {
+    return Ok(None);
+  }
+
+  let mut dialect = builtin.unwrap_or_default().merge(&options.dialect);
+  if let Some(configured) = configured {
+    dialect = dialect.merge(configured);
+  }
+  Ok(Some(dialect.merge(&Dialect::from_query(query)?)))
+}
+
+pub struct ReadDsvParams<'a> {
+  pub resolved_path: &'a str,
+  pub content: &'a str,
+  pub dialect: &'a Dialect,
+  pub has_headers: bool,
+  pub infer_types: bool,
+  pub strict: bool,
+}
+
+/// Reads the records of a delimiter-separated file, returning the table and the
+/// number of malformed rows skipped when not `strict`.
+pub fn read_dsv(params: ReadDsvParams) -> Result<(Table, usize), CompilationError> {
+  let parse_error =
+    |err: &csv::Error| csv_error_to_compilation_error(params.resolved_path, params.content, err);
+  let mut reader = params
+    .dialect
+    .reader_builder()
+    .map_err(|err| CompilationError::TransformError {
+      resolved_path: params.resolved_path.to_string(),
+      msg: err.to_string(),
+    })?
+    .has_headers(params.has_headers)
+    .from_reader(params.content.as_bytes());
+
+  let headers = if params.has_headers {
+    Some(
+      reader
+        .headers()
+        .map_err(|err| parse_error(&err))?
+        .iter()
+        .map(|header| header.to_string())
+        .collect::<Vec<_>>(),
+    )
+  } else {
+    None
+  };
+
+  let mut rows = vec![];
+  let mut skipped = 0;
+  for result in reader.records() {
+    let record = match result {
+      Ok(record) => record,
+      Err(err) if params.strict => return Err(parse_error(&err)),
+      Err(_) => {
+        skipped += 1;
+        continue;
+      }
+    };
+    rows.push(
+      record
+        .iter()
+        .map(|field| infer_cell(field, params.infer_types))
+        .collect(),
+    );
+  }
+
+  Ok((Table { headers, rows }, skipped))
+}
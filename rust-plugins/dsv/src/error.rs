@@ -0,0 +1,84 @@
+use csv::ErrorKind;
+use farmfe_core::error::CompilationError;
+
+/// Converts an error of the csv reader into a [`CompilationError::ParseError`]
+/// pointing at the offending row of `content`.
+pub fn csv_error_to_compilation_error(
+  resolved_path: &str,
+  content: &str,
+  err: &csv::Error,
+) -> CompilationError {
+  let reason = match err.kind() {
+    ErrorKind::UnequalLengths {
+      expected_len, len, ..
+    } => format!(
+      "found a row with {len} fields, but the previous rows have {expected_len} fields. Set `flexible: true` to allow rows of different lengths"
+    ),
+    ErrorKind::Utf8 { err, .. } => format!("invalid UTF-8 in field {}", err.field() + 1),
+    _ => err.to_string(),
+  };
+
+  let msg = match err.position() {
+    Some(pos) => {
+      let line = pos.line() as usize;
+      let column = column_of(content, line, pos.byte() as usize);
+      format!(
+        "Failed to parse {resolved_path}:{line}:{column}: {reason}\n\n{}",
+        code_frame(content, line, column)
+      )
+    }
+    None => format!("Failed to parse {resolved_path}: {reason}"),
+  };
+
+  CompilationError::ParseError {
+    resolved_path: resolved_path.to_string(),
+    msg,
+  }
+}
+
+/// 1-based column of the byte offset `byte`, which lies on the 1-based `line`.
+fn column_of(content: &str, line: usize, byte: usize) -> usize {
+  let line_start = content
+    .match_indices('\n')
+    .nth(line.saturating_sub(2))
+    .filter(|_| line > 1)
+    .map(|(index, _)| index + 1)
+    .unwrap_or(0);
+  content
+    .get(line_start..byte.max(line_start))
+    .map(|prefix| prefix.chars().count() + 1)
+    .unwrap_or(1)
+}
+
+fn code_frame(content: &str, line: usize, column: usize) -> String {
+  let Some(source) = content.lines().nth(line.saturating_sub(1)) else {
+    return String::new();
+  };
+  let gutter = line.to_string().len();
+  format!(
+    "{line} | {source}\n{} | {}^",
+    " ".repeat(gutter),
+    " ".repeat(column.saturating_sub(1))
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_unequal_lengths_error() {
+    let content = "type,count\napples,7\npears\n";
+    let err = csv::Reader::from_reader(content.as_bytes())
+      .records()
+      .find_map(|record| record.err())
+      .unwrap();
+    let CompilationError::ParseError { msg, .. } =
+      csv_error_to_compilation_error("fruit.csv", content, &err)
+    else {
+      panic!("expected a parse error");
+    };
+    assert!(msg.starts_with("Failed to parse fruit.csv:3:1: found a row with 1 fields"));
+    assert!(msg.ends_with("3 | pears\n  | ^"));
+  }
+}
@@ -0,0 +1,294 @@
+use std::{
+  collections::BTreeMap,
+  fs, io,
+  path::{Path, PathBuf},
+};
+
+use farmfe_core::serde_json;
+
+use crate::{
+  codegen::{column_exports, sheet_exports},
+  infer::Cell,
+  table::Table,
+};
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Dts {
+  /// `true` writes a `<name>.d.<ext>.ts` declaration next to every file
+  Bool(bool),
+  /// one declaration file, relative to the project root, for all files
+  Filename(String),
+}
+
+impl Default for Dts {
+  fn default() -> Self {
+    Dts::Bool(false)
+  }
+}
+
+const HEADER: &str = "/* generated by farmfe_plugin_dsv */\n";
+
+fn cell_type(cell: &Cell) -> &'static str {
+  match cell {
+    Cell::Null => "null",
+    Cell::Bool(_) => "boolean",
+    Cell::Number(_) => "number",
+    Cell::String(_) => "string",
+    Cell::Date(_) => "Date",
+    Cell::Json(_) => "unknown",
+  }
+}
+
+/// Union of the types of `cells`, `string` when there are none.
+pub fn stringify_union<'a>(cells: impl Iterator<Item = &'a Cell>) -> String {
+  let mut types = cells.map(cell_type).collect::<Vec<_>>();
+  if types.is_empty() {
+    return "string".to_string();
+  }
+  let order = ["string", "number", "boolean", "Date", "unknown", "null"];
+  types.sort_by_key(|ty| order.iter().position(|o| o == ty));
+  types.dedup();
+  types.join(" | ")
+}
+
+/// Type of the default export: an array of objects typed from the header names
+/// and the types of each column, or an array of field arrays without headers.
+pub fn stringify_rows_type(headers: Option<&[String]>, rows: &[Vec<Cell>]) -> String {
+  match headers {
+    Some(headers) => {
+      let fields = headers
+        .iter()
+        .enumerate()
+        .map(|(index, header)| {
+          format!(
+            "  {}: {};\n",
+            serde_json::to_string(header).unwrap(),
+            stringify_union(rows.iter().filter_map(|row| row.get(index)))
+          )
+        })
+        .collect::<String>();
+      format!("Array<{{\n{fields}}}>")
+    }
+    None => format!("Array<Array<{}>>", stringify_union(rows.iter().flatten())),
+  }
+}
+
+/// Types of the exports of a transformed file.
+pub struct ModuleTypes {
+  pub default: String,
+  /// `(name, local, type)` of every column or sheet export
+  pub named: Vec<(String, String, String)>,
+}
+
+pub fn module_types(
+  headers: Option<&[String]>,
+  rows: &[Vec<Cell>],
+  named_exports: bool,
+) -> ModuleTypes {
+  const NULL: Cell = Cell::Null;
+  let named = match headers {
+    Some(headers) if named_exports => column_exports(headers)
+      .into_iter()
+      .map(|export| {
+        let ty = stringify_union(
+          rows
+            .iter()
+            .map(|row| row.get(export.index).unwrap_or(&NULL)),
+        );
+        (export.name, export.local, format!("Array<{ty}>"))
+      })
+      .collect(),
+    _ => vec![],
+  };
+  ModuleTypes {
+    default: stringify_rows_type(headers, rows),
+    named,
+  }
+}
+
+/// Types of a workbook imported without `?sheet`, the first sheet being the
+/// default export.
+pub fn workbook_types(sheets: &[(String, Table)]) -> ModuleTypes {
+  let rows_type = |table: &Table| stringify_rows_type(table.headers.as_deref(), &table.rows);
+  ModuleTypes {
+    default: sheets
+      .first()
+      .map_or("Array<never>".to_string(), |(_, table)| rows_type(table)),
+    named: sheet_exports(sheets)
+      .into_iter()
+      .map(|export| {
+        let ty = rows_type(&sheets[export.index].1);
+        (export.name, export.local, ty)
+      })
+      .collect(),
+  }
+}
+
+/// Statements declaring the exports of a module. Inside `declare module` the
+/// context is already ambient, so the `declare` modifier must be left out.
+fn stringify_exports(types: &ModuleTypes, ambient: bool) -> Vec<String> {
+  let declare = if ambient { "" } else { "declare " };
+  let mut exports = vec![
+    format!("{declare}const data: {};", types.default),
+    "export default data;".to_string(),
+  ];
+  for (name, local, ty) in &types.named {
+    if name == local {
+      exports.push(format!("export {declare}const {local}: {ty};"));
+    } else {
+      exports.push(format!("{declare}const {local}: {ty};"));
+      exports.push(format!(
+        "export {{ {local} as {} }};",
+        serde_json::to_string(name).unwrap()
+      ));
+    }
+  }
+  exports
+}
+
+/// `data/fruit.csv` -> `data/fruit.d.csv.ts`, the declaration file TypeScript
+/// looks up for arbitrary extensions with `allowArbitraryExtensions`.
+pub fn sidecar_dts_path(resolved_path: &str) -> Option<PathBuf> {
+  let path = Path::new(resolved_path);
+  let stem = path.file_stem()?.to_str()?;
+  let ext = path.extension()?.to_str()?;
+  Some(path.with_file_name(format!("{stem}.d.{ext}.ts")))
+}
+
+pub fn stringify_module_dts(types: &ModuleTypes) -> String {
+  let exports = stringify_exports(types, false).join("\n");
+  format!("{HEADER}{exports}\n")
+}
+
+/// Ambient module declarations of the aggregated `dts` file, keyed by the
+/// path of the file relative to the root.
+#[derive(Debug, Default)]
+pub struct AmbientModules {
+  modules: BTreeMap<String, String>,
+}
+
+impl AmbientModules {
+  pub fn insert(&mut self, relative_path: &str, types: &ModuleTypes) {
+    let exports = stringify_exports(types, true)
+      .iter()
+      .map(|line| indent(line))
+      .collect::<Vec<_>>()
+      .join("\n");
+    self.modules.insert(relative_path.to_string(), exports);
+  }
+
+  /// Returns whether the file had a declaration
+  pub fn remove(&mut self, relative_path: &str) -> bool {
+    self.modules.remove(relative_path).is_some()
+  }
+
+  /// One `declare module '*/<name>'` block per file. Files sharing a name are
+  /// told apart by their parent directories, e.g. `*/a/data.csv` and
+  /// `*/b/data.csv`.
+  pub fn stringify(&self) -> String {
+    let modules = self
+      .modules
+      .iter()
+      .map(|(path, exports)| {
+        format!(
+          "declare module {} {{\n{exports}\n}}\n",
+          serde_json::to_string(&format!("*/{}", self.unique_suffix(path))).unwrap()
+        )
+      })
+      .collect::<Vec<_>>()
+      .join("\n");
+    format!("{HEADER}{modules}")
+  }
+
+  /// The shortest trailing part of `path` no other file ends with
+  fn unique_suffix<'a>(&self, path: &'a str) -> &'a str {
+    path
+      .rmatch_indices('/')
+      .map(|(index, _)| &path[index + 1..])
+      .find(|suffix| {
+        !self
+          .modules
+          .keys()
+          .any(|other| other != path && (other == suffix || other.ends_with(&format!("/{suffix}"))))
+      })
+      .unwrap_or(path)
+  }
+}
+
+fn indent(code: &str) -> String {
+  code
+    .lines()
+    .map(|line| format!("  {line}"))
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Writes `content` unless the file is already up to date, so unchanged
+/// declarations do not trigger the watchers of editors and type checkers.
+pub fn write_dts<P: AsRef<Path>>(path: P, content: &str) -> io::Result<()> {
+  let path = path.as_ref();
+  if fs::read_to_string(path).is_ok_and(|existing| existing == content) {
+    return Ok(());
+  }
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  fs::write(path, content)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::infer::infer_cell;
+
+  #[test]
+  fn test_generate_dts() {
+    let headers = vec!["type".to_string(), "count".to_string()];
+    let rows = vec![
+      vec![infer_cell("apples", true), infer_cell("7", true)],
+      vec![infer_cell("pears", true), infer_cell("", true)],
+    ];
+    let types = module_types(Some(&headers), &rows, true);
+    assert_eq!(
+      types.default,
+      "Array<{\n  \"type\": string;\n  \"count\": number | null;\n}>"
+    );
+    assert_eq!(
+      stringify_module_dts(&types)
+        .lines()
+        .skip(5)
+        .collect::<Vec<_>>(),
+      vec![
+        "export default data;",
+        "export declare const type: Array<string>;",
+        "export declare const count: Array<number | null>;",
+      ]
+    );
+    assert_eq!(
+      sidecar_dts_path("/root/data/fruit.csv").unwrap(),
+      PathBuf::from("/root/data/fruit.d.csv.ts")
+    );
+  }
+
+  #[test]
+  fn test_ambient_modules() {
+    let types = module_types(None, &[vec![Cell::String("apples".to_string())]], true);
+    let mut modules = AmbientModules::default();
+    modules.insert("data/fruit.csv", &types);
+    modules.insert("src/a/data.csv", &types);
+    modules.insert("src/b/data.csv", &types);
+    let code = modules.stringify();
+    assert!(code.contains(
+      "declare module \"*/fruit.csv\" {\n  const data: Array<Array<string>>;\n  export default data;\n}\n"
+    ));
+    assert!(code.contains("declare module \"*/a/data.csv\""));
+    assert!(code.contains("declare module \"*/b/data.csv\""));
+
+    assert!(modules.remove("src/b/data.csv"));
+    assert!(!modules.remove("src/b/data.csv"));
+    assert!(modules
+      .stringify()
+      .contains("declare module \"*/data.csv\""));
+  }
+}
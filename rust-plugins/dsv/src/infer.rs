@@ -0,0 +1,119 @@
+use farmfe_core::serde_json::{self, Number};
+use farmfe_toolkit::{lazy_static::lazy_static, regex::Regex};
+
+lazy_static! {
+  static ref ISO_DATE_REGEX: Regex = Regex::new(
+    r"^\d{4}-(0[1-9]|1[0-2])-(0[1-9]|[12]\d|3[01])(T([01]\d|2[0-3]):[0-5]\d(:[0-5]\d(\.\d+)?)?(Z|[+-]([01]\d|2[0-3]):?[0-5]\d)?)?$"
+  )
+  .unwrap();
+}
+
+/// A single field of a parsed row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+  Null,
+  Bool(bool),
+  Number(Number),
+  String(String),
+  /// An ISO 8601 date or date-time, emitted as `new Date(...)`.
+  Date(String),
+  /// A nested value of a JSON source, emitted as is.
+  Json(serde_json::Value),
+}
+
+impl Cell {
+  pub fn to_js(&self) -> String {
+    match self {
+      Cell::Null => "null".to_string(),
+      Cell::Bool(b) => b.to_string(),
+      Cell::Number(n) => n.to_string(),
+      Cell::String(s) => serde_json::to_string(s).unwrap(),
+      // pure so that unused column exports containing dates can still be tree shaken
+      Cell::Date(s) => format!(
+        "/* @__PURE__ */ new Date({})",
+        serde_json::to_string(s).unwrap()
+      ),
+      Cell::Json(value) => value.to_string(),
+    }
+  }
+
+  /// JSON has no dates, they are kept as their ISO 8601 string.
+  pub fn to_json(&self) -> serde_json::Value {
+    match self {
+      Cell::Null => serde_json::Value::Null,
+      Cell::Bool(b) => serde_json::Value::Bool(*b),
+      Cell::Number(n) => serde_json::Value::Number(n.clone()),
+      Cell::String(s) | Cell::Date(s) => serde_json::Value::String(s.clone()),
+      Cell::Json(value) => value.clone(),
+    }
+  }
+}
+
+/// Converts a raw field into a [`Cell`]. Without `infer_types` every field
+/// stays a string, which is what the plugin has always produced.
+pub fn infer_cell(field: &str, infer_types: bool) -> Cell {
+  if !infer_types {
+    return Cell::String(field.to_string());
+  }
+  if field.is_empty() {
+    return Cell::Null;
+  }
+  match field {
+    "true" | "TRUE" | "True" => return Cell::Bool(true),
+    "false" | "FALSE" | "False" => return Cell::Bool(false),
+    _ => {}
+  }
+  if let Some(number) = parse_number(field) {
+    return Cell::Number(number);
+  }
+  if ISO_DATE_REGEX.is_match(field) {
+    return Cell::Date(field.to_string());
+  }
+  Cell::String(field.to_string())
+}
+
+fn parse_number(field: &str) -> Option<Number> {
+  let digits = field.strip_prefix('-').unwrap_or(field);
+  // keep values such as zip codes (`007`) or `.5` as strings, they are rarely meant as numbers
+  if digits.is_empty()
+    || !digits.starts_with(|c: char| c.is_ascii_digit())
+    || (digits.len() > 1 && digits.starts_with('0') && !digits[1..].starts_with('.'))
+  {
+    return None;
+  }
+  if let Ok(int) = field.parse::<i64>() {
+    return Some(int.into());
+  }
+  if !field
+    .chars()
+    .all(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+  {
+    return None;
+  }
+  field.parse::<f64>().ok().and_then(Number::from_f64)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_infer_cell() {
+    assert_eq!(infer_cell("42", false), Cell::String("42".to_string()));
+    assert_eq!(infer_cell("", true), Cell::Null);
+    assert_eq!(infer_cell("TRUE", true), Cell::Bool(true));
+    assert_eq!(infer_cell("-42", true), Cell::Number((-42).into()));
+    assert_eq!(infer_cell("3.5e2", true).to_js(), "350.0");
+    assert_eq!(infer_cell("0.25", true).to_js(), "0.25");
+    assert_eq!(infer_cell("007", true), Cell::String("007".to_string()));
+    assert_eq!(infer_cell("NaN", true), Cell::String("NaN".to_string()));
+    assert_eq!(
+      infer_cell("2024-02-29T10:00:00Z", true).to_js(),
+      "/* @__PURE__ */ new Date(\"2024-02-29T10:00:00Z\")"
+    );
+    assert_eq!(
+      infer_cell("2024-13-01", true),
+      Cell::String("2024-13-01".to_string())
+    );
+  }
+}
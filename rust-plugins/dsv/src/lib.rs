@@ -1,105 +1,507 @@
 #![deny(clippy::all)]
+mod codegen;
+mod dialect;
+mod dsv;
+mod error;
+mod generate_dts;
+mod infer;
+mod ndjson;
+mod query;
+mod spreadsheet;
+mod table;
 
-use farmfe_toolkit::plugin_utils::path_filter::PathFilter;
-use serde_json::Value;
-use std::{error::Error, path::Path};
+use codegen::{
+  stringify_column_exports, stringify_lazy_loader, stringify_rows, stringify_workbook,
+};
+use dialect::Dialect;
+use dsv::{get_dialect, read_dsv, ReadDsvParams};
+use farmfe_toolkit::{
+  fs::{transform_output_filename, TransformOutputFileNameParams},
+  plugin_utils::path_filter::PathFilter,
+};
+use generate_dts::{AmbientModules, Dts, ModuleTypes};
+use ndjson::{read_ndjson, NDJSON_EXTENSIONS};
+use query::RowQuery;
+use spreadsheet::{read_workbook, SPREADSHEET_EXTENSIONS};
+use std::{
+  collections::HashMap,
+  path::Path,
+  sync::{Arc, Mutex},
+};
+use table::{Source, Table};
 
-use csv::{Reader, ReaderBuilder};
 use farmfe_core::{
+  cache_item,
   config::{config_regex::ConfigRegex, Config},
-  context::CompilationContext,
-  error::Result as HookResult,
-  module::ModuleType,
-  plugin::{Plugin, PluginTransformHookParam, PluginTransformHookResult},
-  serde_json,
+  context::{CompilationContext, EmitFileParams},
+  deserialize,
+  error::{CompilationError, Result as HookResult},
+  module::{ModuleId, ModuleType},
+  plugin::{
+    Plugin, PluginHookContext, PluginLoadHookParam, PluginLoadHookResult,
+    PluginModuleGraphUpdatedHookParam, PluginTransformHookParam, PluginTransformHookResult,
+  },
+  resource::{Resource, ResourceOrigin, ResourceType},
+  serde_json, serialize, Cacheable,
 };
 
 use farmfe_macro_plugin::farm_plugin;
 
 #[derive(serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Options {
   pub include: Option<Vec<ConfigRegex>>,
   pub exclude: Option<Vec<ConfigRegex>>,
+  /// treat the first row as a header and export rows as objects keyed by it
+  pub headers: Option<bool>,
+  /// convert numbers, booleans, empty fields and ISO dates to their JS values
+  pub infer_types: Option<bool>,
+  /// default dialect (`delimiter`, `quote`, `escape`, `comment`, `trim`, `flexible`) of every file
+  #[serde(flatten)]
+  pub dialect: Dialect,
+  /// extra extensions to handle, or overrides of the `csv`/`tsv` dialects, keyed by extension
+  pub extensions: Option<HashMap<String, Dialect>>,
+  /// fail the build on malformed rows instead of skipping them with a warning
+  pub strict: Option<bool>,
+  /// generate TypeScript declarations, next to every file or into a single file
+  pub dts: Option<Dts>,
+  /// also export every column by name, e.g. `export const price = [...]`
+  pub named_exports: Option<bool>,
+}
+
+#[cache_item]
+struct CachedLazyAssets {
+  list: Vec<Resource>,
 }
 
 #[farm_plugin]
 pub struct FarmPluginDsv {
   options: Options,
+  /// ambient module declarations of the aggregated `dts` file
+  declarations: Arc<Mutex<AmbientModules>>,
 }
 
 impl FarmPluginDsv {
   fn new(_config: &Config, options: String) -> Self {
     let options: Options = serde_json::from_str(&options).unwrap();
-    Self { options }
+    Self {
+      options,
+      declarations: Arc::new(Mutex::new(AmbientModules::default())),
+    }
+  }
+
+  fn generate_dts(
+    &self,
+    resolved_path: &str,
+    root_path: &str,
+    types: &ModuleTypes,
+  ) -> std::io::Result<()> {
+    match self.options.dts.clone().unwrap_or_default() {
+      Dts::Bool(false) => Ok(()),
+      Dts::Bool(true) => match generate_dts::sidecar_dts_path(resolved_path) {
+        Some(dts_path) => {
+          generate_dts::write_dts(dts_path, &generate_dts::stringify_module_dts(types))
+        }
+        None => Ok(()),
+      },
+      Dts::Filename(filename) => {
+        let relative_path = ModuleId::new(resolved_path, "", root_path)
+          .relative_path()
+          .to_string();
+        let code = {
+          let mut declarations = self.declarations.lock().unwrap();
+          declarations.insert(&relative_path, types);
+          declarations.stringify()
+        };
+        generate_dts::write_dts(Path::new(root_path).join(filename), &code)
+      }
+    }
+  }
+
+  /// Reads the file with the reader of its extension. Returns `None` for files
+  /// this plugin does not handle, and the number of malformed rows skipped.
+  fn read_source(
+    &self,
+    param: &PluginTransformHookParam,
+    extname: &str,
+  ) -> HookResult<Option<(Source, usize)>> {
+    let options = &self.options;
+    let has_headers = options.headers.unwrap_or(true);
+    let infer_types = options.infer_types.unwrap_or(false);
+    let strict = options.strict.unwrap_or(true);
+    let transform_error = |msg: String| CompilationError::TransformError {
+      resolved_path: param.resolved_path.to_string(),
+      msg,
+    };
+
+    if SPREADSHEET_EXTENSIONS.contains(&extname) {
+      let sheet = param
+        .query
+        .iter()
+        .find(|(k, _)| k == "sheet")
+        .map(|(_, v)| v.as_str());
+      let source = read_workbook(param.resolved_path, sheet, has_headers, infer_types)?;
+      return Ok(Some((source, 0)));
+    }
+
+    if NDJSON_EXTENSIONS.contains(&extname) {
+      let (table, skipped) = read_ndjson(
+        param.resolved_path,
+        &param.content,
+        has_headers,
+        infer_types,
+        strict,
+      )?;
+      return Ok(Some((Source::Table(table), skipped)));
+    }
+
+    let dialect = match get_dialect(param.resolved_path, &param.query, options) {
+      Ok(Some(dialect)) => dialect,
+      // without an explicit `include` every module reaches this hook, so only
+      // complain about files the user asked this plugin to handle
+      Ok(None)
+        if options
+          .include
+          .as_ref()
+          .is_none_or(|include| include.is_empty()) =>
+      {
+        return Ok(None)
+      }
+      Ok(None) => {
+        return Err(transform_error(
+          "Unsupported file type, map its extension to a dialect with the `extensions` option"
+            .to_string(),
+        ))
+      }
+      Err(err) => return Err(transform_error(err.to_string())),
+    };
+    let (table, skipped) = read_dsv(ReadDsvParams {
+      resolved_path: param.resolved_path,
+      content: &param.content,
+      dialect: &dialect,
+      has_headers,
+      infer_types,
+      strict,
+    })?;
+    Ok(Some((Source::Table(table), skipped)))
   }
 }
-struct Param {
-  module_id: String,
-  content: String,
+
+/// Queries handled by Farm's asset plugins, e.g. `./fruit.csv?url`
+const ASSET_QUERIES: [&str; 3] = ["url", "raw", "inline"];
+
+fn is_asset_query(query: &[(String, String)]) -> bool {
+  query
+    .iter()
+    .any(|(key, _)| ASSET_QUERIES.contains(&key.as_str()))
 }
 
-fn get_reader(param: &Param) -> Result<Reader<&[u8]>, Box<dyn Error>> {
-  let extname = Path::new(&param.module_id)
-    .extension()
-    .ok_or("No file extension found")?
-    .to_str()
-    .ok_or("File extension cannot be converted to string")?;
-
-  let reader = match extname {
-    "csv" => ReaderBuilder::new().from_reader(param.content.as_bytes()),
-    "tsv" => ReaderBuilder::new()
-      .delimiter(b'\t')
-      .from_reader(param.content.as_bytes()),
-    _ => return Err("Unsupported file type".into()),
-  };
+/// Whether a module id query string, e.g. `?lazy&sheet=a`, has the `key` parameter
+fn has_query_key(query_string: &str, key: &str) -> bool {
+  query_string
+    .trim_start_matches('?')
+    .split('&')
+    .any(|pair| pair.split('=').next() == Some(key))
+}
 
-  Ok(reader)
+fn extname(resolved_path: &str) -> &str {
+  Path::new(resolved_path)
+    .extension()
+    .and_then(|ext| ext.to_str())
+    .unwrap_or_default()
 }
 
 impl Plugin for FarmPluginDsv {
   fn name(&self) -> &str {
     "FarmPluginDsv"
   }
+
+  /// Spreadsheets are binary files, they are read from their path in the
+  /// transform hook instead of being loaded as text.
+  fn load(
+    &self,
+    param: &PluginLoadHookParam,
+    _context: &Arc<CompilationContext>,
+    _hook_context: &PluginHookContext,
+  ) -> HookResult<Option<PluginLoadHookResult>> {
+    if !SPREADSHEET_EXTENSIONS.contains(&extname(param.resolved_path))
+      || is_asset_query(&param.query)
+    {
+      return Ok(None);
+    }
+    let include = self.options.include.clone().unwrap_or_default();
+    let exclude = self.options.exclude.clone().unwrap_or_default();
+    let filter = PathFilter::new(&include, &exclude);
+    if !filter.execute(&param.module_id) {
+      return Ok(None);
+    }
+
+    Ok(Some(PluginLoadHookResult {
+      content: String::new(),
+      module_type: ModuleType::Custom("dsv".to_string()),
+      source_map: None,
+    }))
+  }
+
   fn transform(
     &self,
     param: &PluginTransformHookParam,
-    _context: &std::sync::Arc<CompilationContext>,
+    context: &Arc<CompilationContext>,
   ) -> HookResult<Option<PluginTransformHookResult>> {
-    let options = self.options.clone();
-    let include = options.include.unwrap_or(vec![]);
-    let exclude = options.exclude.unwrap_or(vec![]);
+    let options = &self.options;
+    let include = options.include.clone().unwrap_or_default();
+    let exclude = options.exclude.clone().unwrap_or_default();
     let filter = PathFilter::new(&include, &exclude);
-    if !filter.execute(&param.module_id) {
+    if !filter.execute(&param.module_id) || is_asset_query(&param.query) {
       return Ok(None);
     }
 
-    let binding = Param {
-      module_id: param.module_id.clone(),
-      content: param.content.clone(),
+    let Some((source, skipped)) = self.read_source(param, extname(param.resolved_path))? else {
+      return Ok(None);
     };
 
-    let reader = get_reader(&binding);
-
-    if reader.is_err() {
-      return Ok(None);
+    // the declarations describe the file imported without a query, so every
+    // variant writes the same ones. A single sheet does not describe the workbook.
+    if !param.query.iter().any(|(k, _)| k == "sheet") {
+      let types = match &source {
+        Source::Table(Table { headers, rows }) => generate_dts::module_types(
+          headers.as_deref(),
+          rows,
+          options.named_exports.unwrap_or(false),
+        ),
+        Source::Workbook(sheets) => generate_dts::workbook_types(sheets),
+      };
+      self
+        .generate_dts(param.resolved_path, &context.config.root, &types)
+        .map_err(|err| CompilationError::TransformError {
+          resolved_path: param.resolved_path.to_string(),
+          msg: format!("Failed to write the type declarations: {err}"),
+        })?;
     }
 
-    let mut records = vec![];
-    for result in reader.unwrap().records() {
-      let record = result.unwrap();
-      let json_record: Value = record
-        .iter()
-        .map(|field| Value::String(field.to_string()))
-        .collect();
-      records.push(json_record);
+    let source = RowQuery::from_query(&param.query)
+      .and_then(|row_query| source.try_map(|table| row_query.apply(table)))
+      .map_err(|err| CompilationError::TransformError {
+        resolved_path: param.resolved_path.to_string(),
+        msg: err.to_string(),
+      })?;
+
+    if skipped > 0 {
+      context.log_store.lock().add_warning(format!(
+        "[{}] Skipped {skipped} malformed rows in {}",
+        self.name(),
+        param.resolved_path
+      ));
     }
-    let json_string = serde_json::to_string(&records).unwrap();
+
+    let content = if param.query.iter().any(|(k, _)| k == "lazy") {
+      let url = emit_lazy_asset(param, context, &source);
+      let mode = context
+        .config
+        .assets
+        .mode
+        .unwrap_or_else(|| context.config.output.target_env.clone().into());
+      stringify_lazy_loader(&url, mode)
+    } else {
+      match &source {
+        Source::Table(Table { headers, rows }) => {
+          let mut content = format!(
+            "export default {};\n",
+            stringify_rows(headers.as_deref(), rows)
+          );
+          if let (Some(headers), true) = (headers, options.named_exports.unwrap_or(false)) {
+            content.push_str(&stringify_column_exports(headers, rows));
+          }
+          content
+        }
+        Source::Workbook(sheets) => stringify_workbook(sheets),
+      }
+    };
 
     Ok(Some(PluginTransformHookResult {
-      content: format!("export default {}", json_string),
-      module_type: Some(ModuleType::Custom("json".to_string())),
+      content,
+      module_type: Some(ModuleType::Js),
       source_map: None,
       ignore_previous_source_map: true,
     }))
   }
+
+  /// Drops the declarations of removed files from the aggregated `dts` file
+  fn module_graph_updated(
+    &self,
+    param: &PluginModuleGraphUpdatedHookParam,
+    context: &Arc<CompilationContext>,
+  ) -> HookResult<Option<()>> {
+    let Some(Dts::Filename(filename)) = &self.options.dts else {
+      return Ok(None);
+    };
+    let code = {
+      let module_graph = context.module_graph.read();
+      let modules = module_graph.modules();
+      let mut declarations = self.declarations.lock().unwrap();
+      let mut removed = false;
+      for module_id in &param.removed_modules_ids {
+        // other imports of the file, such as `?columns=` variants, keep it
+        let path = module_id.relative_path();
+        if !modules
+          .iter()
+          .any(|module| module.id.relative_path() == path)
+        {
+          removed |= declarations.remove(path);
+        }
+      }
+      if !removed {
+        return Ok(None);
+      }
+      declarations.stringify()
+    };
+    generate_dts::write_dts(Path::new(&context.config.root).join(filename), &code).map_err(
+      |err| CompilationError::GenericError(format!("Failed to write the type declarations: {err}")),
+    )?;
+    Ok(Some(()))
+  }
+
+  fn plugin_cache_loaded(
+    &self,
+    cache: &Vec<u8>,
+    context: &Arc<CompilationContext>,
+  ) -> HookResult<Option<()>> {
+    let cached_lazy_assets = deserialize!(cache, CachedLazyAssets);
+
+    for asset in cached_lazy_assets.list {
+      if let ResourceOrigin::Module(m) = asset.origin {
+        context.emit_file(EmitFileParams {
+          resolved_path: m.to_string(),
+          name: asset.name,
+          content: asset.bytes,
+          resource_type: asset.resource_type,
+        });
+      }
+    }
+
+    Ok(Some(()))
+  }
+
+  fn write_plugin_cache(&self, context: &Arc<CompilationContext>) -> HookResult<Option<Vec<u8>>> {
+    let mut list = vec![];
+    let resources_map = context.resources_map.lock();
+    for (_, resource) in resources_map.iter() {
+      if let ResourceOrigin::Module(m) = &resource.origin {
+        if has_query_key(m.query_string(), "lazy")
+          && context.cache_manager.module_cache.has_cache(m)
+          && matches!(&resource.resource_type, ResourceType::Asset(ext) if ext == "json")
+        {
+          list.push(resource.clone());
+        }
+      }
+    }
+
+    if !list.is_empty() {
+      let cached_lazy_assets = CachedLazyAssets { list };
+      Ok(Some(serialize!(&cached_lazy_assets)))
+    } else {
+      Ok(None)
+    }
+  }
+}
+
+/// Emits the rows of a `?lazy` import as a JSON asset and returns its public url.
+/// A workbook imported without `?sheet` emits its first sheet.
+fn emit_lazy_asset(
+  param: &PluginTransformHookParam,
+  context: &Arc<CompilationContext>,
+  source: &Source,
+) -> String {
+  let bytes = source.to_json_bytes();
+  let name = Path::new(param.resolved_path)
+    .file_stem()
+    .and_then(|stem| stem.to_str())
+    .unwrap_or("data");
+  let resource_name = transform_output_filename(TransformOutputFileNameParams {
+    filename_config: context.config.output.assets_filename.clone(),
+    name,
+    name_hash: "",
+    bytes: &bytes,
+    ext: "json",
+    special_placeholders: &Default::default(),
+  });
+  let url = if !context.config.output.public_path.is_empty() {
+    let public_path = context.config.output.public_path.trim_end_matches('/');
+    format!("{public_path}/{resource_name}")
+  } else {
+    format!("/{resource_name}")
+  };
+
+  context.emit_file(EmitFileParams {
+    resolved_path: param.module_id.clone(),
+    name: resource_name,
+    content: bytes,
+    resource_type: ResourceType::Asset("json".to_string()),
+  });
+
+  url
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_queried_imports_keep_declarations() {
+    let dir = std::env::temp_dir().join("farmfe_plugin_dsv_dts");
+    let resolved_path = dir.join("fruit.csv").to_string_lossy().to_string();
+    let plugin = FarmPluginDsv::new(&Config::default(), r#"{"dts": true}"#.to_string());
+    let context = Arc::new(CompilationContext::new(Config::default(), vec![]).unwrap());
+    plugin
+      .transform(
+        &PluginTransformHookParam {
+          module_id: "fruit.csv?columns=name".to_string(),
+          content: "name,price\napples,7\n".to_string(),
+          module_type: ModuleType::Custom("csv".to_string()),
+          resolved_path: &resolved_path,
+          query: vec![("columns".to_string(), "name".to_string())],
+          meta: Default::default(),
+          source_map_chain: vec![],
+        },
+        &context,
+      )
+      .unwrap();
+    let dts = std::fs::read_to_string(dir.join("fruit.d.csv.ts")).unwrap();
+    assert!(dts.contains("\"name\": string;\n  \"price\": string;"));
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn test_asset_queries() {
+    let plugin = FarmPluginDsv::new(&Config::default(), "{}".to_string());
+    let context = Arc::new(CompilationContext::new(Config::default(), vec![]).unwrap());
+    let transform = |query: &[(&str, &str)]| {
+      plugin
+        .transform(
+          &PluginTransformHookParam {
+            module_id: "fruit.csv".to_string(),
+            content: "name,price\napples,7\n".to_string(),
+            module_type: ModuleType::Custom("csv".to_string()),
+            resolved_path: "/root/fruit.csv",
+            query: query
+              .iter()
+              .map(|(k, v)| (k.to_string(), v.to_string()))
+              .collect(),
+            meta: Default::default(),
+            source_map_chain: vec![],
+          },
+          &context,
+        )
+        .unwrap()
+    };
+    assert!(transform(&[("url", "")]).is_none());
+    assert!(transform(&[("raw", "")]).is_none());
+    assert!(transform(&[("inline", "")]).is_none());
+    assert!(transform(&[]).is_some_and(|result| result.content.starts_with("export default [")));
+
+    assert!(has_query_key("?lazy", "lazy"));
+    assert!(has_query_key("?sheet=a&lazy", "lazy"));
+    assert!(!has_query_key("?sheet=lazy", "lazy"));
+    assert!(!has_query_key("?lazyness", "lazy"));
+  }
 }
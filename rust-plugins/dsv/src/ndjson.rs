@@ -0,0 +1,168 @@
+use farmfe_core::{
+  error::CompilationError,
+  serde_json::{self, Value},
+};
+
+use crate::{
+  infer::{infer_cell, Cell},
+  table::Table,
+};
+
+pub const NDJSON_EXTENSIONS: &[&str] = &["ndjson", "jsonl"];
+
+fn json_to_cell(value: Value, infer_types: bool) -> Cell {
+  match value {
+    Value::Null => Cell::Null,
+    Value::Bool(b) => Cell::Bool(b),
+    Value::Number(n) => Cell::Number(n),
+    // strings only go through inference for dates, other types are already typed
+    Value::String(s) => match infer_cell(&s, infer_types) {
+      date @ Cell::Date(_) => date,
+      _ => Cell::String(s),
+    },
+    value => Cell::Json(value),
+  }
+}
+
+fn json_to_header(value: &Value) -> String {
+  match value {
+    Value::String(s) => s.clone(),
+    value => value.to_string(),
+  }
+}
+
+/// Reads newline-delimited JSON. Lines holding objects become rows keyed by
+/// every key seen in the file, in order of appearance, keys missing from a
+/// line being `null`. Lines holding arrays are read like the records of a
+/// CSV file. Returns the table and the number of invalid lines skipped when not
+/// `strict`.
+pub fn read_ndjson(
+  resolved_path: &str,
+  content: &str,
+  has_headers: bool,
+  infer_types: bool,
+  strict: bool,
+) -> Result<(Table, usize), CompilationError> {
+  let mut keys: Vec<String> = vec![];
+  let mut lines = vec![];
+  let mut skipped = 0;
+
+  for (index, line) in content.lines().enumerate() {
+    if line.trim().is_empty() {
+      continue;
+    }
+    let value = match serde_json::from_str::<Value>(line) {
+      Ok(value @ (Value::Object(_) | Value::Array(_))) => value,
+      Ok(_) if strict => {
+        return Err(line_error(
+          resolved_path,
+          index + 1,
+          1,
+          line,
+          "expected a JSON object or array",
+        ))
+      }
+      Err(err) if strict => {
+        return Err(line_error(
+          resolved_path,
+          index + 1,
+          err.column(),
+          line,
+          &err.to_string(),
+        ))
+      }
+      _ => {
+        skipped += 1;
+        continue;
+      }
+    };
+    if let Value::Object(object) = &value {
+      for key in object.keys() {
+        if !keys.contains(key) {
+          keys.push(key.clone());
+        }
+      }
+    }
+    lines.push(value);
+  }
+
+  let mut headers = if has_headers && !keys.is_empty() {
+    Some(keys.clone())
+  } else {
+    None
+  };
+  let mut rows = vec![];
+  for value in lines {
+    match value {
+      Value::Object(mut object) => rows.push(
+        keys
+          .iter()
+          .map(|key| json_to_cell(object.remove(key).unwrap_or(Value::Null), infer_types))
+          .collect(),
+      ),
+      Value::Array(values) if has_headers && headers.is_none() => {
+        headers = Some(values.iter().map(json_to_header).collect());
+      }
+      Value::Array(values) => rows.push(
+        values
+          .into_iter()
+          .map(|value| json_to_cell(value, infer_types))
+          .collect(),
+      ),
+      _ => unreachable!(),
+    }
+  }
+
+  Ok((Table { headers, rows }, skipped))
+}
+
+fn line_error(
+  resolved_path: &str,
+  line: usize,
+  column: usize,
+  source: &str,
+  reason: &str,
+) -> CompilationError {
+  let gutter = line.to_string().len();
+  CompilationError::ParseError {
+    resolved_path: resolved_path.to_string(),
+    msg: format!(
+      "Failed to parse {resolved_path}:{line}:{column}: {reason}\n\n{line} | {source}\n{} | {}^",
+      " ".repeat(gutter),
+      " ".repeat(column.saturating_sub(1))
+    ),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_read_ndjson() {
+    let content = "{\"id\":1,\"tags\":[\"a\"]}\n\n{\"id\":2,\"at\":\"2024-01-31\"}\n";
+    let (table, _) = read_ndjson("events.ndjson", content, true, true, true).unwrap();
+    assert_eq!(
+      table.headers,
+      Some(vec!["id".to_string(), "tags".to_string(), "at".to_string()])
+    );
+    assert_eq!(table.rows[0][1].to_js(), "[\"a\"]");
+    assert_eq!(table.rows[0][2], Cell::Null);
+    assert_eq!(table.rows[1][2], Cell::Date("2024-01-31".to_string()));
+
+    let (table, _) = read_ndjson("rows.ndjson", "[\"id\"]\n[1]", true, false, true).unwrap();
+    assert_eq!(table.headers, Some(vec!["id".to_string()]));
+    assert_eq!(table.rows, vec![vec![Cell::Number(1.into())]]);
+  }
+
+  #[test]
+  fn test_invalid_ndjson() {
+    let content = "{\"id\":1}\n{\"id\":\n";
+    assert!(matches!(
+      read_ndjson("events.ndjson", content, true, false, true),
+      Err(CompilationError::ParseError { msg, .. }) if msg.contains("events.ndjson:2:")
+    ));
+    let (table, skipped) = read_ndjson("events.ndjson", content, true, false, false).unwrap();
+    assert_eq!((table.rows.len(), skipped), (1, 1));
+  }
+}
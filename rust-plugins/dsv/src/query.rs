@@ -0,0 +1,311 @@
+use std::{cmp::Ordering, error::Error};
+
+use percent_encoding::percent_decode_str;
+
+use crate::{infer::Cell, table::Table};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+  Eq,
+  Ne,
+  Gt,
+  Ge,
+  Lt,
+  Le,
+}
+
+#[derive(Debug, PartialEq)]
+struct Condition {
+  column: String,
+  operator: Operator,
+  value: String,
+}
+
+impl Condition {
+  /// `active:true`, `price:gte:10`, `name:ne:pears`, `price>10`... Farm
+  /// rejects a query value containing a plain `=`, so the operators holding
+  /// one are spelled out, their symbols are only read once percent-decoded.
+  fn parse(condition: &str) -> Result<Self, Box<dyn Error>> {
+    // two-character operators first, so `>=` is not read as `>`
+    const OPERATORS: &[(&str, Operator)] = &[
+      ("!=", Operator::Ne),
+      (">=", Operator::Ge),
+      ("<=", Operator::Le),
+      ("=", Operator::Eq),
+      (":", Operator::Eq),
+      (">", Operator::Gt),
+      ("<", Operator::Lt),
+    ];
+    let (index, symbol, operator) = OPERATORS
+      .iter()
+      .filter_map(|(symbol, operator)| {
+        condition
+          .find(symbol)
+          .map(|index| (index, *symbol, *operator))
+      })
+      .min_by_key(|(index, symbol, _)| (*index, std::cmp::Reverse(symbol.len())))
+      .ok_or_else(|| {
+        format!(
+          "Invalid `where` condition `{condition}`, expected e.g. `active:true` or `price:gte:10`"
+        )
+      })?;
+    let column = condition[..index].trim();
+    if column.is_empty() {
+      return Err(format!("Invalid `where` condition `{condition}`, the column is missing").into());
+    }
+    let mut value = condition[index + symbol.len()..].trim();
+    let mut operator = operator;
+    if symbol == ":" {
+      const NAMED_OPERATORS: &[(&str, Operator)] = &[
+        ("eq:", Operator::Eq),
+        ("ne:", Operator::Ne),
+        ("gt:", Operator::Gt),
+        ("gte:", Operator::Ge),
+        ("lt:", Operator::Lt),
+        ("lte:", Operator::Le),
+      ];
+      if let Some((name, named)) = NAMED_OPERATORS
+        .iter()
+        .find(|(name, _)| value.starts_with(name))
+      {
+        operator = *named;
+        value = value[name.len()..].trim();
+      }
+    }
+    Ok(Self {
+      column: column.to_string(),
+      operator,
+      value: value.to_string(),
+    })
+  }
+
+  fn matches(&self, cell: &Cell) -> bool {
+    let ordering = compare(cell, &self.value);
+    match self.operator {
+      Operator::Eq => ordering == Some(Ordering::Equal),
+      Operator::Ne => ordering != Some(Ordering::Equal),
+      Operator::Gt => ordering == Some(Ordering::Greater),
+      Operator::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+      Operator::Lt => ordering == Some(Ordering::Less),
+      Operator::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+    }
+  }
+}
+
+fn cell_text(cell: &Cell) -> String {
+  match cell {
+    Cell::Null => String::new(),
+    Cell::Bool(b) => b.to_string(),
+    Cell::Number(n) => n.to_string(),
+    Cell::String(s) | Cell::Date(s) => s.clone(),
+    Cell::Json(value) => value.to_string(),
+  }
+}
+
+/// Compares numerically when both sides are numbers, whether or not types are
+/// inferred, and as text otherwise, which also orders ISO dates. `null` only
+/// equals an empty value.
+fn compare(cell: &Cell, value: &str) -> Option<Ordering> {
+  if let Cell::Null = cell {
+    return value.is_empty().then_some(Ordering::Equal);
+  }
+  let text = cell_text(cell);
+  match (text.parse::<f64>(), value.parse::<f64>()) {
+    (Ok(a), Ok(b)) => a.partial_cmp(&b),
+    _ => Some(text.as_str().cmp(value)),
+  }
+}
+
+/// Build-time projection and filtering of the rows, from the `columns` and
+/// `where` query parameters, e.g. `?columns=id,name&where=active:true`.
+#[derive(Debug, Default, PartialEq)]
+pub struct RowQuery {
+  columns: Option<Vec<String>>,
+  conditions: Vec<Condition>,
+}
+
+impl RowQuery {
+  pub fn from_query(query: &[(String, String)]) -> Result<Self, Box<dyn Error>> {
+    let mut row_query = Self::default();
+    for (key, value) in query {
+      let value = percent_decode_str(value).decode_utf8()?;
+      match key.as_str() {
+        "columns" => {
+          row_query.columns = Some(
+            value
+              .split(',')
+              .map(|column| column.trim().to_string())
+              .filter(|column| !column.is_empty())
+              .collect(),
+          )
+        }
+        // every `where` parameter and every comma separated condition must match
+        "where" => {
+          for condition in value.split(',').filter(|c| !c.trim().is_empty()) {
+            row_query.conditions.push(Condition::parse(condition)?);
+          }
+        }
+        _ => {}
+      }
+    }
+    Ok(row_query)
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.columns.is_none() && self.conditions.is_empty()
+  }
+
+  /// Filters the rows then keeps the selected columns, in the order of the
+  /// query. Columns are header names, or indexes when there are no headers.
+  pub fn apply(&self, table: Table) -> Result<Table, Box<dyn Error>> {
+    if self.is_empty() {
+      return Ok(table);
+    }
+    let Table { headers, rows } = table;
+    let column_index = |column: &str| -> Result<usize, Box<dyn Error>> {
+      let index = match &headers {
+        Some(headers) => headers.iter().rposition(|header| header == column),
+        None => column.parse::<usize>().ok(),
+      };
+      index.ok_or_else(|| {
+        let available = match &headers {
+          Some(headers) => headers.join(", "),
+          None => "column indexes when `headers` is false".to_string(),
+        };
+        format!("Unknown column `{column}`, available columns: {available}").into()
+      })
+    };
+
+    let conditions = self
+      .conditions
+      .iter()
+      .map(|condition| Ok((column_index(&condition.column)?, condition)))
+      .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+    let rows = rows.into_iter().filter(|row| {
+      conditions
+        .iter()
+        .all(|(index, condition)| condition.matches(row.get(*index).unwrap_or(&Cell::Null)))
+    });
+
+    let Some(columns) = &self.columns else {
+      return Ok(Table {
+        headers,
+        rows: rows.collect(),
+      });
+    };
+    let indexes = columns
+      .iter()
+      .map(|column| column_index(column))
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Table {
+      headers: headers.as_ref().map(|_| columns.clone()),
+      rows: rows
+        .map(|row| {
+          indexes
+            .iter()
+            .map(|index| row.get(*index).cloned().unwrap_or(Cell::Null))
+            .collect()
+        })
+        .collect(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::infer::infer_cell;
+
+  fn query(pairs: &[(&str, &str)]) -> RowQuery {
+    let pairs = pairs
+      .iter()
+      .map(|(k, v)| (k.to_string(), v.to_string()))
+      .collect::<Vec<_>>();
+    RowQuery::from_query(&pairs).unwrap()
+  }
+
+  #[test]
+  fn test_apply_row_query() {
+    let table = || Table {
+      headers: Some(["id", "name", "active", "price"].map(String::from).to_vec()),
+      rows: [
+        ["1", "apples", "true", "7"],
+        ["2", "pears", "false", "4.5"],
+        ["3", "kiwis", "true", "12"],
+      ]
+      .iter()
+      .map(|row| row.iter().map(|field| infer_cell(field, true)).collect())
+      .collect(),
+    };
+
+    let result = query(&[("columns", "name,id"), ("where", "active%3Dtrue")])
+      .apply(table())
+      .unwrap();
+    assert_eq!(
+      result.headers,
+      Some(vec!["name".to_string(), "id".to_string()])
+    );
+    assert_eq!(
+      result.rows,
+      vec![
+        vec![Cell::String("apples".to_string()), Cell::Number(1.into())],
+        vec![Cell::String("kiwis".to_string()), Cell::Number(3.into())],
+      ]
+    );
+
+    let result = query(&[("where", "price>4.5,price<=12"), ("where", "name!=kiwis")])
+      .apply(table())
+      .unwrap();
+    assert_eq!(result.rows.len(), 1);
+    assert_eq!(result.rows[0][1], Cell::String("apples".to_string()));
+
+    assert!(query(&[("columns", "weight")]).apply(table()).is_err());
+  }
+
+  #[test]
+  fn test_query_from_import_path() {
+    let path = "./orders.csv?columns=name&where=price:gte:7,price%3C%3D12&where=name:ne:kiwis";
+    let result = RowQuery::from_query(&farmfe_utils::parse_query(path))
+      .unwrap()
+      .apply(Table {
+        headers: Some(vec!["name".to_string(), "price".to_string()]),
+        rows: [["apples", "7"], ["pears", "4.5"], ["kiwis", "12"]]
+          .iter()
+          .map(|row| row.iter().map(|field| infer_cell(field, true)).collect())
+          .collect(),
+      })
+      .unwrap();
+    assert_eq!(result.rows, vec![vec![Cell::String("apples".to_string())]]);
+  }
+
+  #[test]
+  fn test_parse_condition() {
+    assert_eq!(
+      Condition::parse("price >= 10").unwrap(),
+      Condition {
+        column: "price".to_string(),
+        operator: Operator::Ge,
+        value: "10".to_string(),
+      }
+    );
+    assert_eq!(
+      Condition::parse("active:true").unwrap().operator,
+      Operator::Eq
+    );
+    assert_eq!(
+      Condition::parse("date:lte:2024-01-01").unwrap(),
+      Condition {
+        column: "date".to_string(),
+        operator: Operator::Le,
+        value: "2024-01-01".to_string(),
+      }
+    );
+    assert_eq!(
+      Condition::parse("name:ne:kiwis").unwrap().operator,
+      Operator::Ne
+    );
+    assert_eq!(Condition::parse("time:12:30").unwrap().value, "12:30");
+    assert!(Condition::parse("active").is_err());
+    assert!(Condition::parse(">10").is_err());
+  }
+}
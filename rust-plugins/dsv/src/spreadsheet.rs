@@ -0,0 +1,136 @@
+use calamine::{open_workbook_auto, Data, Range, Reader};
+use farmfe_core::{error::CompilationError, serde_json::Number};
+
+use crate::{
+  infer::{infer_cell, Cell},
+  table::{Source, Table},
+};
+
+pub const SPREADSHEET_EXTENSIONS: &[&str] = &["xlsx", "xlsm", "xlsb", "xls", "ods"];
+
+/// Spreadsheet cells are already typed, so only text cells go through
+/// inference, and only to recognize dates.
+fn data_to_cell(data: &Data, infer_types: bool) -> Cell {
+  match data {
+    Data::Int(i) => Cell::Number((*i).into()),
+    // whole numbers are stored as floats, export them as integers
+    Data::Float(f) if f.fract() == 0.0 && f.abs() < (1i64 << 53) as f64 => {
+      Cell::Number((*f as i64).into())
+    }
+    Data::Float(f) => Number::from_f64(*f).map_or(Cell::Null, Cell::Number),
+    Data::Bool(b) => Cell::Bool(*b),
+    Data::String(s) => match infer_cell(s, infer_types) {
+      date @ Cell::Date(_) => date,
+      _ => Cell::String(s.clone()),
+    },
+    Data::DateTime(datetime) if !datetime.is_duration() => match datetime.as_datetime() {
+      Some(datetime) => Cell::Date(datetime.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+      None => Cell::String(datetime.to_string()),
+    },
+    Data::DateTimeIso(s) => Cell::Date(s.clone()),
+    Data::DateTime(_) | Data::DurationIso(_) | Data::Error(_) => Cell::String(data.to_string()),
+    Data::Empty => infer_cell("", infer_types),
+  }
+}
+
+fn range_to_table(range: &Range<Data>, has_headers: bool, infer_types: bool) -> Table {
+  let mut rows = range.rows();
+  let headers = if has_headers {
+    rows
+      .next()
+      .map(|row| row.iter().map(|data| data.to_string()).collect())
+  } else {
+    None
+  };
+  Table {
+    headers,
+    rows: rows
+      .map(|row| {
+        row
+          .iter()
+          .map(|data| data_to_cell(data, infer_types))
+          .collect()
+      })
+      .collect(),
+  }
+}
+
+/// Reads the sheet named `sheet`, or every sheet of the workbook.
+pub fn read_workbook(
+  resolved_path: &str,
+  sheet: Option<&str>,
+  has_headers: bool,
+  infer_types: bool,
+) -> Result<Source, CompilationError> {
+  let parse_error = |msg: String| CompilationError::ParseError {
+    resolved_path: resolved_path.to_string(),
+    msg,
+  };
+  let mut workbook = open_workbook_auto(resolved_path)
+    .map_err(|err| parse_error(format!("Failed to read workbook {resolved_path}: {err}")))?;
+
+  match sheet {
+    Some(sheet) => {
+      let sheet_names = workbook.sheet_names();
+      if !sheet_names.iter().any(|name| name == sheet) {
+        return Err(parse_error(format!(
+          "Sheet `{sheet}` not found in {resolved_path}, available sheets: {}",
+          sheet_names.join(", ")
+        )));
+      }
+      let range = workbook
+        .worksheet_range(sheet)
+        .map_err(|err| parse_error(format!("Failed to read sheet `{sheet}`: {err}")))?;
+      Ok(Source::Table(range_to_table(
+        &range,
+        has_headers,
+        infer_types,
+      )))
+    }
+    None => Ok(Source::Workbook(
+      workbook
+        .worksheets()
+        .iter()
+        .map(|(name, range)| {
+          (
+            name.clone(),
+            range_to_table(range, has_headers, infer_types),
+          )
+        })
+        .collect(),
+    )),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_range_to_table() {
+    let mut range = Range::new((0, 0), (2, 1));
+    range.set_value((0, 0), Data::String("name".to_string()));
+    range.set_value((0, 1), Data::String("price".to_string()));
+    range.set_value((1, 0), Data::String("apples".to_string()));
+    range.set_value((1, 1), Data::Float(7.0));
+    range.set_value((2, 0), Data::String("2024-01-31".to_string()));
+    range.set_value((2, 1), Data::Float(0.5));
+
+    let table = range_to_table(&range, true, true);
+    assert_eq!(
+      table.headers,
+      Some(vec!["name".to_string(), "price".to_string()])
+    );
+    assert_eq!(
+      table.rows,
+      vec![
+        vec![Cell::String("apples".to_string()), Cell::Number(7.into())],
+        vec![
+          Cell::Date("2024-01-31".to_string()),
+          Cell::Number(Number::from_f64(0.5).unwrap())
+        ],
+      ]
+    );
+    assert_eq!(range_to_table(&range, false, false).rows.len(), 3);
+  }
+}
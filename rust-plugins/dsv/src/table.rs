@@ -0,0 +1,72 @@
+use farmfe_core::serde_json::{self, Value};
+
+use crate::infer::Cell;
+
+/// Rows read from a file or a sheet, the shape every format is converted to
+/// before generating code and declarations.
+#[derive(Debug, Default, PartialEq)]
+pub struct Table {
+  /// `None` when rows are exported as arrays of fields
+  pub headers: Option<Vec<String>>,
+  pub rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+  pub fn to_json(&self) -> Value {
+    Value::Array(
+      self
+        .rows
+        .iter()
+        .map(|row| match &self.headers {
+          Some(headers) => Value::Object(
+            headers
+              .iter()
+              .zip(row)
+              .map(|(key, cell)| (key.clone(), cell.to_json()))
+              .collect(),
+          ),
+          None => Value::Array(row.iter().map(Cell::to_json).collect()),
+        })
+        .collect(),
+    )
+  }
+}
+
+/// What a file exports by default: a single table, or the sheets of a
+/// workbook imported without `?sheet`, the first one being the default export.
+pub enum Source {
+  Table(Table),
+  Workbook(Vec<(String, Table)>),
+}
+
+impl Source {
+  pub fn default_table(&self) -> Option<&Table> {
+    match self {
+      Source::Table(table) => Some(table),
+      Source::Workbook(sheets) => sheets.first().map(|(_, table)| table),
+    }
+  }
+
+  /// Applies `f` to the table, or to every sheet of the workbook.
+  pub fn try_map<E>(self, f: impl Fn(Table) -> Result<Table, E>) -> Result<Self, E> {
+    Ok(match self {
+      Source::Table(table) => Source::Table(f(table)?),
+      Source::Workbook(sheets) => Source::Workbook(
+        sheets
+          .into_iter()
+          .map(|(name, table)| Ok((name, f(table)?)))
+          .collect::<Result<_, E>>()?,
+      ),
+    })
+  }
+
+  pub fn to_json(&self) -> Value {
+    self
+      .default_table()
+      .map_or(Value::Array(vec![]), Table::to_json)
+  }
+
+  pub fn to_json_bytes(&self) -> Vec<u8> {
+    serde_json::to_vec(&self.to_json()).unwrap()
+  }
+}
@@ -9,4 +9,151 @@ export interface IPluginOptions {
   pragmaImportSource?: String,
   jsxImportSource?: String,
   filepath?: String,
+  /**
+   * Export the fields of the YAML/TOML frontmatter as variables usable in the MDX body,
+   * e.g. `{title}` instead of `{frontmatter.title}`
+   * @default false
+   */
+  frontmatterVariables?: boolean,
+  /**
+   * Export `toc`, a nested array of `{ depth, text, id, children }` built from the headings,
+   * and set the matching `id` on every rendered heading. `false` disables both
+   * @default true
+   */
+  toc?: boolean | {
+    /** @default 1 */
+    minDepth?: number,
+    /** @default 6 */
+    maxDepth?: number,
+    /**
+     * `github` keeps unicode letters like GitHub does, `ascii` transliterates them first
+     * @default 'github'
+     */
+    slugger?: 'github' | 'ascii',
+  },
+  /**
+   * Highlight fenced code blocks at build time with Sublime Text grammars and TextMate themes.
+   * `{1,3-4}` in the meta string highlights lines and `title="app.js"` adds a caption
+   * @default false
+   */
+  highlight?: boolean | {
+    /**
+     * A built-in theme, e.g. `InspiredGitHub`, `Solarized (dark)`, `base16-ocean.dark`,
+     * or the name of a `.tmTheme` file in `themes`
+     * @default 'InspiredGitHub'
+     */
+    theme?: string,
+    /**
+     * `inline` sets `style` on every token, `classes` sets the TextMate scopes as class names
+     * @default 'inline'
+     */
+    mode?: 'inline' | 'classes',
+    /** prefix of the class names in `classes` mode */
+    classPrefix?: string,
+    /** folder of extra `.sublime-syntax` grammars, relative to the root, `.tmLanguage` files are not read */
+    syntaxes?: string,
+    /** folder of extra `.tmTheme` themes, relative to the root */
+    themes?: string,
+    /** in `classes` mode, write the stylesheet of `theme` to this file, relative to the root */
+    css?: string,
+  },
+  /**
+   * Render plain Markdown to an HTML string instead of compiling it as MDX, so `<` and `{`
+   * are just text. Such modules export `html` (also the default export), `frontmatter` and `toc`.
+   * `true` selects `.md` files
+   * @default false
+   */
+  markdown?: boolean | {
+    /**
+     * files with these extensions are rendered to HTML
+     * @default ['.md']
+     */
+    extensions?: string[],
+    /** globs relative to the root, matching files are rendered to HTML too */
+    files?: string[],
+    /**
+     * keep raw HTML instead of escaping it, only for trusted content
+     * @default false
+     */
+    allowDangerousHtml?: boolean,
+  },
+  /**
+   * Import relative image sources, e.g. `![](./diagram.png)`, so the image goes through the
+   * asset pipeline and gets a hashed url
+   * @default true
+   */
+  images?: boolean,
+  /**
+   * Check that relative links to `.md`/`.mdx` files point to existing files, and report
+   * broken ones as warnings or as errors that fail the build
+   * @default 'warn'
+   */
+  checkLinks?: boolean | 'warn' | 'error',
+  /**
+   * Preset for the UI framework: sets `jsxImportSource` (automatic runtime), imports
+   * `useMDXComponents` from the framework's provider package (`@mdx-js/react`, `@mdx-js/preact`,
+   * `@mdx-js/vue` or `solid-mdx`) and, for Vue, exports a component made with `defineComponent`.
+   * `jsxImportSource` and `providerImportSource` take precedence over the preset
+   * @default 'react'
+   */
+  framework?: 'react' | 'preact' | 'vue' | 'solid',
+  /**
+   * Content collections, imported as `mdx-collection:<name>`. The module exports the sorted
+   * index of the pages of `dir`, with their frontmatter, slug, word count and reading time,
+   * and a lazy `load()` of each page. Pages are not bundled into the index
+   */
+  collections?: Record<string, {
+    /** directory of the pages, relative to the root */
+    dir: string,
+    /**
+     * glob of the pages, relative to `dir`
+     * @default '**\/*.{md,mdx}'
+     */
+    pattern?: string,
+    /**
+     * frontmatter field the index is sorted by, entries without it come last
+     * @default 'date'
+     */
+    sortBy?: string,
+    /** @default 'desc' */
+    order?: 'asc' | 'desc',
+    /**
+     * declared frontmatter fields, checked at build time and used to type the index.
+     * Append `?` for optional fields, e.g. `{ title: 'string', tags: 'string[]?' }`
+     */
+    schema?: Record<string, FieldType | `${FieldType}?`>,
+    /** @default 200 */
+    wordsPerMinute?: number,
+  }>,
+  /**
+   * Write the declarations of the `collections` modules to this file, relative to the root.
+   * `true` writes `mdx-collections.d.ts`
+   * @default true
+   */
+  collectionsDts?: boolean | string,
+  /**
+   * Layout components by name, e.g. `{ post: './src/layouts/Post.tsx' }`. Paths starting with
+   * `.` are relative to the root, other sources are resolved as usual. A page with
+   * `layout: post` in its frontmatter is wrapped in the layout, which receives the props of the
+   * page, `frontmatter`, `toc` and the content as `children`
+   */
+  layouts?: Record<string, string>,
+  /**
+   * Layout of the pages of a directory, relative to the root, e.g. `{ 'src/posts': 'post' }`.
+   * The deepest directory wins, `layout: false` in the frontmatter opts out
+   */
+  defaultLayouts?: Record<string, string>,
+  /**
+   * Built-in transforms of `.mdx` and `.md` files compiled as MDX:
+   * - `gfm`: tables, strikethrough, task lists, footnotes and autolink literals
+   * - `headingAutolinks`: an empty `<a className="heading-anchor">` at the end of each heading
+   * - `externalLinks`: `target="_blank"` and `rel="noopener noreferrer"` on links to other sites
+   * - `emoji`: GitHub shortcodes such as `:tada:` to emoji
+   * - `math`: `$...$` and `$$` blocks to MathML, with KaTeX's class names
+   * - `admonitions`: `:::note Title` ... `:::` to `<div className="admonition admonition-note">`
+   * @default []
+   */
+  transforms?: Array<'gfm' | 'headingAutolinks' | 'externalLinks' | 'emoji' | 'math' | 'admonitions'>,
 }
+
+type FieldType = 'string' | 'number' | 'boolean' | 'date' | 'string[]' | 'number[]';
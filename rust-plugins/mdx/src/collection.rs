@@ -0,0 +1,546 @@
+use std::{
+  cmp::Ordering,
+  collections::{BTreeMap, HashMap},
+  fs::{read_dir, read_to_string},
+  path::{Path, PathBuf},
+};
+
+use farmfe_core::{
+  error::CompilationError,
+  serde_json::{self, Map, Value},
+};
+use globset::{Glob, GlobMatcher};
+use lazy_static::lazy_static;
+use markdown::mdast::Node;
+use mdxjs::MdxParseOptions;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  frontmatter::parse_frontmatter,
+  mdast::{to_mdast, walk},
+};
+
+/// `import posts from 'mdx-collection:blog'`
+pub const COLLECTION_PREFIX: &str = "mdx-collection:";
+
+lazy_static! {
+  static ref DATE: Regex = Regex::new(r"^\d{4}-\d{2}-\d{2}").unwrap();
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SortOrder {
+  Asc,
+  #[default]
+  Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+  String,
+  Number,
+  Boolean,
+  /// `YYYY-MM-DD`, optionally followed by a time
+  Date,
+  StringArray,
+  NumberArray,
+}
+
+/// Type of a frontmatter field, e.g. `string`, `date` or `string[]?` for an
+/// optional list of strings
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub struct FieldType {
+  pub kind: FieldKind,
+  pub optional: bool,
+}
+
+impl TryFrom<String> for FieldType {
+  type Error = String;
+
+  fn try_from(value: String) -> Result<Self, Self::Error> {
+    let (name, optional) = match value.strip_suffix('?') {
+      Some(name) => (name, true),
+      None => (value.as_str(), false),
+    };
+    let kind = match name {
+      "string" => FieldKind::String,
+      "number" => FieldKind::Number,
+      "boolean" => FieldKind::Boolean,
+      "date" => FieldKind::Date,
+      "string[]" => FieldKind::StringArray,
+      "number[]" => FieldKind::NumberArray,
+      _ => {
+        return Err(format!(
+          "unknown field type `{value}`, expected string, number, boolean, date, string[] or number[]"
+        ))
+      }
+    };
+    Ok(Self { kind, optional })
+  }
+}
+
+impl FieldType {
+  fn matches(&self, value: &Value) -> bool {
+    match (self.kind, value) {
+      (FieldKind::String, Value::String(_)) => true,
+      (FieldKind::Number, Value::Number(_)) => true,
+      (FieldKind::Boolean, Value::Bool(_)) => true,
+      (FieldKind::Date, Value::String(s)) => DATE.is_match(s),
+      (FieldKind::StringArray, Value::Array(items)) => items.iter().all(Value::is_string),
+      (FieldKind::NumberArray, Value::Array(items)) => items.iter().all(Value::is_number),
+      _ => false,
+    }
+  }
+
+  fn ts_type(&self) -> &'static str {
+    match self.kind {
+      FieldKind::String | FieldKind::Date => "string",
+      FieldKind::Number => "number",
+      FieldKind::Boolean => "boolean",
+      FieldKind::StringArray => "string[]",
+      FieldKind::NumberArray => "number[]",
+    }
+  }
+
+  fn name(&self) -> &'static str {
+    match self.kind {
+      FieldKind::String => "a string",
+      FieldKind::Number => "a number",
+      FieldKind::Boolean => "a boolean",
+      FieldKind::Date => "a date (YYYY-MM-DD)",
+      FieldKind::StringArray => "a list of strings",
+      FieldKind::NumberArray => "a list of numbers",
+    }
+  }
+}
+
+/// An item of the `collections` option
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionOptions {
+  /// Directory of the entries, relative to the root
+  pub dir: String,
+  /// Glob of the entries, relative to `dir`
+  #[serde(default = "default_pattern")]
+  pub pattern: String,
+  /// Frontmatter field the index is sorted by
+  #[serde(default = "default_sort_by")]
+  pub sort_by: String,
+  #[serde(default)]
+  pub order: SortOrder,
+  /// Declared frontmatter fields, entries are checked against them
+  #[serde(default)]
+  pub schema: BTreeMap<String, FieldType>,
+  #[serde(default = "default_words_per_minute")]
+  pub words_per_minute: usize,
+}
+
+fn default_pattern() -> String {
+  "**/*.{md,mdx}".to_string()
+}
+
+fn default_sort_by() -> String {
+  "date".to_string()
+}
+
+fn default_words_per_minute() -> usize {
+  200
+}
+
+/// `collectionsDts` option, `true` writes `mdx-collections.d.ts` in the root
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum DtsConfig {
+  Enabled(bool),
+  Path(String),
+}
+
+impl DtsConfig {
+  pub fn path(&self) -> Option<&str> {
+    match self {
+      DtsConfig::Enabled(true) => Some("mdx-collections.d.ts"),
+      DtsConfig::Enabled(false) => None,
+      DtsConfig::Path(path) => Some(path),
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+  pub slug: String,
+  #[serde(skip)]
+  pub path: PathBuf,
+  pub frontmatter: Map<String, Value>,
+  pub word_count: usize,
+  /// Minutes, at least 1
+  pub reading_time: usize,
+}
+
+#[derive(Debug)]
+pub struct Collection {
+  name: String,
+  dir: PathBuf,
+  matcher: GlobMatcher,
+  options: CollectionOptions,
+}
+
+impl Collection {
+  pub fn new(name: &str, options: &CollectionOptions, root: &str) -> Result<Self, String> {
+    let matcher = Glob::new(&options.pattern)
+      .map_err(|e| {
+        format!(
+          "Invalid pattern `{}` of collection `{name}`: {e}",
+          options.pattern
+        )
+      })?
+      .compile_matcher();
+    Ok(Self {
+      name: name.to_string(),
+      dir: Path::new(root).join(&options.dir),
+      matcher,
+      options: options.clone(),
+    })
+  }
+
+  /// Whether a change of `path` changes the index: the directory itself, or
+  /// a file under it matching the pattern
+  pub fn contains(&self, path: &Path) -> bool {
+    path == self.dir
+      || path
+        .strip_prefix(&self.dir)
+        .is_ok_and(|relative| self.matcher.is_match(relative))
+  }
+
+  /// Directory of the entries
+  pub fn dir(&self) -> &Path {
+    &self.dir
+  }
+
+  /// Files of the collection, in path order
+  pub fn files(&self) -> Vec<PathBuf> {
+    let mut files = vec![];
+    collect_files(&self.dir, &mut files);
+    files.retain(|path| {
+      path
+        .strip_prefix(&self.dir)
+        .is_ok_and(|relative| self.matcher.is_match(relative))
+    });
+    files.sort();
+    files
+  }
+
+  /// Reads the frontmatter and counts the words of `path`. The body is only
+  /// parsed, it is not part of the index.
+  pub fn entry(&self, path: &Path, parse: &MdxParseOptions) -> Result<Entry, CompilationError> {
+    let resolved_path = path.to_string_lossy().to_string();
+    let content = read_to_string(path).map_err(|e| CompilationError::LoadError {
+      resolved_path: resolved_path.clone(),
+      source: Some(Box::new(e)),
+    })?;
+    let (frontmatter, body) = match parse_frontmatter(&resolved_path, &content)? {
+      Some(frontmatter) => (frontmatter.data, &content[frontmatter.len..]),
+      None => (Map::new(), content.as_str()),
+    };
+    self.validate(&resolved_path, &frontmatter)?;
+
+    let slug = match frontmatter.get("slug") {
+      Some(Value::String(slug)) => slug.clone(),
+      _ => self.slug(path),
+    };
+    let word_count = word_count(body, parse);
+    Ok(Entry {
+      slug,
+      path: path.to_path_buf(),
+      frontmatter,
+      word_count,
+      reading_time: word_count
+        .div_ceil(self.options.words_per_minute.max(1))
+        .max(1),
+    })
+  }
+
+  /// `blog/hello-world.mdx` is `hello-world`, `blog/guide/index.md` is `guide`
+  fn slug(&self, path: &Path) -> String {
+    let relative = path
+      .strip_prefix(&self.dir)
+      .unwrap_or(path)
+      .with_extension("");
+    let mut segments = relative
+      .components()
+      .map(|component| component.as_os_str().to_string_lossy().to_string())
+      .collect::<Vec<_>>();
+    if segments.last().is_some_and(|last| last == "index") {
+      segments.pop();
+    }
+    segments.join("/")
+  }
+
+  fn validate(
+    &self,
+    resolved_path: &str,
+    frontmatter: &Map<String, Value>,
+  ) -> Result<(), CompilationError> {
+    let errors = self
+      .options
+      .schema
+      .iter()
+      .filter_map(|(field, field_type)| match frontmatter.get(field) {
+        None | Some(Value::Null) if field_type.optional => None,
+        None | Some(Value::Null) => Some(format!("`{field}` is required")),
+        Some(value) if !field_type.matches(value) => Some(format!(
+          "`{field}` must be {}, got {value}",
+          field_type.name()
+        )),
+        Some(_) => None,
+      })
+      .collect::<Vec<_>>();
+    if errors.is_empty() {
+      return Ok(());
+    }
+    Err(CompilationError::TransformError {
+      resolved_path: resolved_path.to_string(),
+      msg: format!(
+        "The frontmatter of {resolved_path} does not match the schema of collection `{}`:\n{}",
+        self.name,
+        errors.join("\n")
+      ),
+    })
+  }
+
+  fn compare(&self, a: &Entry, b: &Entry) -> Ordering {
+    let field = &self.options.sort_by;
+    let ordering = match (a.frontmatter.get(field), b.frontmatter.get(field)) {
+      (Some(a), Some(b)) => {
+        let ordering = compare_values(a, b);
+        match self.options.order {
+          SortOrder::Asc => ordering,
+          SortOrder::Desc => ordering.reverse(),
+        }
+      }
+      // entries without the field go last
+      (Some(_), None) => Ordering::Less,
+      (None, Some(_)) => Ordering::Greater,
+      (None, None) => Ordering::Equal,
+    };
+    ordering.then_with(|| a.slug.cmp(&b.slug))
+  }
+
+  /// The index module: the sorted entries, each with a lazy `load` of its page.
+  /// Also returns the files, they are watched by the module.
+  pub fn render(
+    &self,
+    parse: &MdxParseOptions,
+  ) -> Result<(String, Vec<PathBuf>), CompilationError> {
+    let mut entries = self
+      .files()
+      .iter()
+      .map(|path| self.entry(path, parse))
+      .collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by(|a, b| self.compare(a, b));
+
+    let mut code = "const entries = [\n".to_string();
+    for entry in &entries {
+      let mut object = serde_json::to_string(entry).unwrap();
+      let path = entry.path.to_string_lossy().replace('\\', "/");
+      // `{...}` becomes `{..., "load": () => import("...")}`
+      object.pop();
+      code.push_str(&format!(
+        "  {object},\"load\":() => import({})}},\n",
+        serde_json::to_string(&path).unwrap()
+      ));
+    }
+    code.push_str("];\nexport default entries;\n");
+    Ok((code, entries.into_iter().map(|entry| entry.path).collect()))
+  }
+
+  /// `declare module 'mdx-collection:blog'` typing the entries with the schema
+  pub fn dts(&self) -> String {
+    let fields = self
+      .options
+      .schema
+      .iter()
+      .map(|(field, field_type)| {
+        let optional = if field_type.optional { "?" } else { "" };
+        format!(
+          "    {}{optional}: {};\n",
+          serde_json::to_string(field).unwrap(),
+          field_type.ts_type()
+        )
+      })
+      .collect::<String>();
+    format!(
+      "declare module '{COLLECTION_PREFIX}{}' {{\n  \
+         export interface Frontmatter {{\n{fields}    [key: string]: unknown;\n  }}\n  \
+         export interface Entry {{\n    \
+           slug: string;\n    \
+           frontmatter: Frontmatter;\n    \
+           wordCount: number;\n    \
+           /** minutes */\n    \
+           readingTime: number;\n    \
+           load: () => Promise<{{ default: any; frontmatter: Frontmatter; toc?: TocEntry[] }}>;\n  \
+         }}\n  \
+         export interface TocEntry {{\n    \
+           depth: number;\n    \
+           text: string;\n    \
+           id: string;\n    \
+           children: TocEntry[];\n  \
+         }}\n  \
+         const entries: Entry[];\n  \
+         export default entries;\n\
+       }}\n",
+      self.name
+    )
+  }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+  let Ok(entries) = read_dir(dir) else {
+    return;
+  };
+  for entry in entries.flatten() {
+    let path = entry.path();
+    if path.is_dir() {
+      collect_files(&path, files);
+    } else {
+      files.push(path);
+    }
+  }
+}
+
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+  match (a, b) {
+    (Value::Number(a), Value::Number(b)) => a
+      .as_f64()
+      .partial_cmp(&b.as_f64())
+      .unwrap_or(Ordering::Equal),
+    // ISO dates sort as strings
+    (Value::String(a), Value::String(b)) => a.cmp(b),
+    (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+    (a, b) => a.to_string().cmp(&b.to_string()),
+  }
+}
+
+/// Words of the text, code included. Documents MDX can not parse, e.g. `.md`
+/// files with a stray `{`, are counted as plain text.
+pub fn word_count(body: &str, parse: &MdxParseOptions) -> usize {
+  let Ok(mdast) = to_mdast(body, parse) else {
+    return body.split_whitespace().count();
+  };
+  let mut count = 0;
+  walk(&mdast, &mut |node| match node {
+    Node::Text(text) => count += text.value.split_whitespace().count(),
+    Node::InlineCode(code) => count += code.value.split_whitespace().count(),
+    Node::Code(code) => count += code.value.split_whitespace().count(),
+    _ => {}
+  });
+  count
+}
+
+/// Writes the declarations of every collection to `path`
+pub fn write_dts(collections: &HashMap<String, Collection>, path: &Path) -> Result<(), String> {
+  let mut collections = collections.values().collect::<Vec<_>>();
+  collections.sort_by(|a, b| a.name.cmp(&b.name));
+  let code = format!(
+    "/* generated by farmfe_plugin_mdx */\n{}",
+    collections
+      .iter()
+      .map(|collection| collection.dts())
+      .collect::<Vec<_>>()
+      .join("\n")
+  );
+  // keep the file untouched when nothing changed, it would restart type checkers
+  if read_to_string(path).is_ok_and(|current| current == code) {
+    return Ok(());
+  }
+  std::fs::write(path, code).map_err(|e| {
+    format!(
+      "Failed to write the collection declarations {}: {e}",
+      path.display()
+    )
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn collection(test: &str, schema: &str) -> (Collection, PathBuf) {
+    let root = std::env::temp_dir().join(format!("farm-mdx-{test}-{}", std::process::id()));
+    let blog = root.join("blog");
+    std::fs::create_dir_all(blog.join("guide")).unwrap();
+    std::fs::write(
+      blog.join("hello.mdx"),
+      "---\ntitle: Hello\ndate: 2024-01-02\n---\n\n# Hello {1 + 1}\n\nOne two three.\n",
+    )
+    .unwrap();
+    std::fs::write(
+      blog.join("guide/index.md"),
+      "---\ntitle: Guide\ndate: 2024-03-04\n---\n\nA {broken ` word\n",
+    )
+    .unwrap();
+    std::fs::write(blog.join("draft.mdx"), "No frontmatter\n").unwrap();
+    std::fs::write(blog.join("notes.txt"), "ignored").unwrap();
+    let options = serde_json::from_str::<CollectionOptions>(&format!(
+      r#"{{"dir": "blog", "schema": {schema}}}"#
+    ))
+    .unwrap();
+    (
+      Collection::new("blog", &options, &root.to_string_lossy()).unwrap(),
+      blog,
+    )
+  }
+
+  #[test]
+  fn test_render() {
+    let (collection, blog) = collection("render", r#"{"title": "string?", "date": "date?"}"#);
+    let (code, files) = collection.render(&MdxParseOptions::default()).unwrap();
+    let hello = blog.join("hello.mdx").to_string_lossy().to_string();
+    assert_eq!(
+      files,
+      vec![
+        blog.join("guide/index.md"),
+        blog.join("hello.mdx"),
+        blog.join("draft.mdx")
+      ]
+    );
+    assert!(code.starts_with(
+      "const entries = [\n  {\"slug\":\"guide\",\"frontmatter\":{\"title\":\"Guide\",\"date\":\"2024-03-04\"},\
+       \"wordCount\":4,\"readingTime\":1,\"load\":() => import("
+    ));
+    assert!(code.contains(&format!(
+      "{{\"slug\":\"hello\",\"frontmatter\":{{\"title\":\"Hello\",\"date\":\"2024-01-02\"}},\
+       \"wordCount\":4,\"readingTime\":1,\"load\":() => import({})}},\n",
+      serde_json::to_string(&hello.replace('\\', "/")).unwrap()
+    )));
+    assert!(code.ends_with("];\nexport default entries;\n"));
+
+    let dts = collection.dts();
+    assert!(dts.starts_with("declare module 'mdx-collection:blog' {\n"));
+    assert!(dts
+      .contains("    \"date\"?: string;\n    \"title\"?: string;\n    [key: string]: unknown;\n"));
+  }
+
+  #[test]
+  fn test_schema() {
+    let (collection, blog) = collection(
+      "schema",
+      r#"{"title": "string", "date": "date", "tags": "string[]?"}"#,
+    );
+    let Err(CompilationError::TransformError { msg, .. }) =
+      collection.entry(&blog.join("draft.mdx"), &MdxParseOptions::default())
+    else {
+      panic!("expected a schema error");
+    };
+    assert!(
+      msg.ends_with("`date` is required\n`title` is required"),
+      "{msg}"
+    );
+    assert!(serde_json::from_str::<FieldType>("\"object\"").is_err());
+    assert!(FieldType::try_from("date".to_string())
+      .unwrap()
+      .matches(&Value::String("2024-01-02T10:00:00Z".into())));
+  }
+}
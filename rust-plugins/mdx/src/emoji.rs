@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+  pub static ref SHORTCODE: Regex = Regex::new(r":([a-z0-9_+\-]+):").unwrap();
+  static ref EMOJI: HashMap<&'static str, &'static str> = SHORTCODES.iter().copied().collect();
+}
+
+/// The emoji of a GitHub shortcode name, e.g. `tada` for `:tada:`
+pub fn emoji(name: &str) -> Option<&'static str> {
+  EMOJI.get(name).copied()
+}
+
+/// The common GitHub shortcodes
+const SHORTCODES: &[(&str, &str)] = &[
+  ("+1", "👍"),
+  ("-1", "👎"),
+  ("100", "💯"),
+  ("alarm_clock", "⏰"),
+  ("angry", "😠"),
+  ("apple", "🍎"),
+  ("arrow_down", "⬇️"),
+  ("arrow_left", "⬅️"),
+  ("arrow_right", "➡️"),
+  ("arrow_up", "⬆️"),
+  ("art", "🎨"),
+  ("baby", "👶"),
+  ("balloon", "🎈"),
+  ("bangbang", "‼️"),
+  ("battery", "🔋"),
+  ("bee", "🐝"),
+  ("beer", "🍺"),
+  ("bell", "🔔"),
+  ("bike", "🚲"),
+  ("bird", "🐦"),
+  ("blush", "😊"),
+  ("bomb", "💣"),
+  ("book", "📖"),
+  ("books", "📚"),
+  ("boom", "💥"),
+  ("bookmark", "🔖"),
+  ("bug", "🐛"),
+  ("bulb", "💡"),
+  ("cactus", "🌵"),
+  ("cake", "🍰"),
+  ("calendar", "📆"),
+  ("camera", "📷"),
+  ("cat", "🐱"),
+  ("chart_with_upwards_trend", "📈"),
+  ("check", "✔️"),
+  ("clap", "👏"),
+  ("clipboard", "📋"),
+  ("closed_lock_with_key", "🔐"),
+  ("cloud", "☁️"),
+  ("coffee", "☕"),
+  ("computer", "💻"),
+  ("confused", "😕"),
+  ("construction", "🚧"),
+  ("cookie", "🍪"),
+  ("cool", "🆒"),
+  ("cry", "😢"),
+  ("crystal_ball", "🔮"),
+  ("dart", "🎯"),
+  ("dash", "💨"),
+  ("dog", "🐶"),
+  ("dizzy", "💫"),
+  ("earth_africa", "🌍"),
+  ("earth_americas", "🌎"),
+  ("earth_asia", "🌏"),
+  ("email", "📧"),
+  ("exclamation", "❗"),
+  ("eyes", "👀"),
+  ("file_folder", "📁"),
+  ("fire", "🔥"),
+  ("fish", "🐟"),
+  ("flags", "🎏"),
+  ("flashlight", "🔦"),
+  ("floppy_disk", "💾"),
+  ("gear", "⚙️"),
+  ("gem", "💎"),
+  ("ghost", "👻"),
+  ("gift", "🎁"),
+  ("globe_with_meridians", "🌐"),
+  ("green_heart", "💚"),
+  ("grin", "😁"),
+  ("grinning", "😀"),
+  ("hammer", "🔨"),
+  ("hammer_and_wrench", "🛠️"),
+  ("hand", "✋"),
+  ("heart", "❤️"),
+  ("heart_eyes", "😍"),
+  ("heavy_check_mark", "✔️"),
+  ("heavy_minus_sign", "➖"),
+  ("heavy_plus_sign", "➕"),
+  ("hourglass", "⌛"),
+  ("house", "🏠"),
+  ("hugs", "🤗"),
+  ("information_source", "ℹ️"),
+  ("innocent", "😇"),
+  ("joy", "😂"),
+  ("key", "🔑"),
+  ("kiss", "💋"),
+  ("label", "🏷️"),
+  ("laughing", "😆"),
+  ("link", "🔗"),
+  ("lipstick", "💄"),
+  ("lock", "🔒"),
+  ("loudspeaker", "📢"),
+  ("mag", "🔍"),
+  ("mailbox", "📫"),
+  ("memo", "📝"),
+  ("microscope", "🔬"),
+  ("moneybag", "💰"),
+  ("moon", "🌔"),
+  ("muscle", "💪"),
+  ("mute", "🔇"),
+  ("new", "🆕"),
+  ("no_entry", "⛔"),
+  ("no_entry_sign", "🚫"),
+  ("ok", "🆗"),
+  ("ok_hand", "👌"),
+  ("package", "📦"),
+  ("pencil", "📝"),
+  ("pencil2", "✏️"),
+  ("point_down", "👇"),
+  ("point_left", "👈"),
+  ("point_right", "👉"),
+  ("point_up", "☝️"),
+  ("pray", "🙏"),
+  ("pushpin", "📌"),
+  ("question", "❓"),
+  ("rainbow", "🌈"),
+  ("raised_hands", "🙌"),
+  ("recycle", "♻️"),
+  ("red_circle", "🔴"),
+  ("rocket", "🚀"),
+  ("rotating_light", "🚨"),
+  ("scream", "😱"),
+  ("see_no_evil", "🙈"),
+  ("shield", "🛡️"),
+  ("shrug", "🤷"),
+  ("skull", "💀"),
+  ("sleeping", "😴"),
+  ("smile", "😄"),
+  ("smiley", "😃"),
+  ("smirk", "😏"),
+  ("snail", "🐌"),
+  ("snowflake", "❄️"),
+  ("sob", "😭"),
+  ("sparkles", "✨"),
+  ("speech_balloon", "💬"),
+  ("star", "⭐"),
+  ("star2", "🌟"),
+  ("stop_sign", "🛑"),
+  ("sunny", "☀️"),
+  ("sunglasses", "😎"),
+  ("tada", "🎉"),
+  ("thinking", "🤔"),
+  ("thumbsdown", "👎"),
+  ("thumbsup", "👍"),
+  ("trophy", "🏆"),
+  ("warning", "⚠️"),
+  ("wave", "👋"),
+  ("white_check_mark", "✅"),
+  ("wink", "😉"),
+  ("wrench", "🔧"),
+  ("x", "❌"),
+  ("zap", "⚡"),
+];
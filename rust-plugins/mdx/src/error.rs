@@ -0,0 +1,60 @@
+use farmfe_core::error::CompilationError;
+use markdown::{
+  message::{Message, Place},
+  unist::Point,
+};
+
+/// Lines shown before the error line in the code frame
+const CONTEXT_LINES: usize = 2;
+
+/// Turns a markdown/mdxjs message into a [`CompilationError::ParseError`] with
+/// the position and a code frame of `content`.
+pub fn message_to_compilation_error(
+  resolved_path: &str,
+  content: &str,
+  message: &Message,
+) -> CompilationError {
+  let start = message.place.as_deref().map(|place| match place {
+    Place::Position(position) => &position.start,
+    Place::Point(point) => point,
+  });
+  // e.g. `(mdx:expression)`, tells where the message comes from
+  let reason = format!(
+    "{} ({}:{})",
+    message.reason, message.source, message.rule_id
+  );
+  match start {
+    Some(Point { line, column, .. }) => CompilationError::ParseError {
+      resolved_path: resolved_path.to_string(),
+      msg: format!(
+        "Failed to parse {resolved_path}:{line}:{column}: {reason}\n\n{}",
+        code_frame(content, *line, *column)
+      ),
+    },
+    None => CompilationError::ParseError {
+      resolved_path: resolved_path.to_string(),
+      msg: format!("Failed to parse {resolved_path}: {reason}"),
+    },
+  }
+}
+
+fn code_frame(content: &str, line: usize, column: usize) -> String {
+  let lines = content.lines().collect::<Vec<_>>();
+  // an unclosed construct is reported after the last line
+  let line = line.clamp(1, lines.len().max(1));
+  let first = line.saturating_sub(CONTEXT_LINES).max(1);
+  let gutter = line.to_string().len();
+  let mut frame = (first..=line)
+    .filter_map(|number| {
+      lines
+        .get(number - 1)
+        .map(|source| format!("{number:>gutter$} | {source}\n"))
+    })
+    .collect::<String>();
+  frame.push_str(&format!(
+    "{} | {}^",
+    " ".repeat(gutter),
+    " ".repeat(column.saturating_sub(1))
+  ));
+  frame
+}
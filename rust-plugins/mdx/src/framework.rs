@@ -0,0 +1,76 @@
+use serde::Deserialize;
+
+/// `framework` option, a preset for the JSX runtime, the provider and the
+/// component the module exports.
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Framework {
+  #[default]
+  React,
+  Preact,
+  Vue,
+  Solid,
+}
+
+impl Framework {
+  /// `jsxImportSource`, the automatic runtime is imported from `{source}/jsx-runtime`.
+  /// mdxjs imports the classic `pragma` as a default export, which Preact and Vue
+  /// do not have, so the presets only cover the automatic runtime.
+  pub fn jsx_import_source(self) -> &'static str {
+    match self {
+      Framework::React => "react",
+      Framework::Preact => "preact",
+      Framework::Vue => "vue",
+      Framework::Solid => "solid-js/h",
+    }
+  }
+
+  /// Package exporting `useMDXComponents`, used for `providerImportSource`
+  pub fn provider_import_source(self) -> &'static str {
+    match self {
+      Framework::React => "@mdx-js/react",
+      Framework::Preact => "@mdx-js/preact",
+      Framework::Vue => "@mdx-js/vue",
+      Framework::Solid => "solid-mdx",
+    }
+  }
+
+  /// Makes the default export a component of the framework. `MDXContent` is a
+  /// function component, which React, Preact and Solid can mount as it is.
+  pub fn wrap_default_export(self, code: &str) -> String {
+    let wrapper = match self {
+      Framework::React | Framework::Preact | Framework::Solid => return code.to_string(),
+      // props that are not declared end up in `attrs`, `components` included
+      Framework::Vue => {
+        "import { defineComponent as _defineComponent } from \"vue\";\n\
+         export default _defineComponent({\n    \
+           name: \"MDXContent\",\n    \
+           inheritAttrs: false,\n    \
+           setup(_props, { attrs }) {\n        \
+             return () => MDXContent(attrs);\n    \
+           }\n\
+         });"
+      }
+    };
+    // mdxjs always ends the document with this statement
+    code.replacen("export default MDXContent;", wrapper, 1)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_wrap_default_export() {
+    let code = "function MDXContent(props = {}) {}\nexport default MDXContent;\n";
+    assert_eq!(Framework::Preact.wrap_default_export(code), code);
+    let code = Framework::Vue.wrap_default_export(code);
+    assert!(code.starts_with(
+      "function MDXContent(props = {}) {}\n\
+       import { defineComponent as _defineComponent } from \"vue\";\n\
+       export default _defineComponent({\n"
+    ));
+    assert!(!code.contains("export default MDXContent;"));
+  }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashSet;
+
+use farmfe_core::{
+  error::CompilationError,
+  serde_json::{self, Map, Value},
+  swc_ecma_ast::Ident,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Names the compiled module declares besides those starting with `_`, such as
+/// `_components` or `_MDXLayout`.
+const MDX_NAMES: [&str; 4] = ["frontmatter", "toc", "MDXContent", "MDXLayout"];
+
+lazy_static! {
+  static ref EXPORT_DECL: Regex = Regex::new(
+    r"(?m)^export\s+(?:const|let|var|(?:async\s+)?function\*?|class)\s+([\p{L}\p{N}_$]+)"
+  )
+  .unwrap();
+  static ref EXPORT_LIST: Regex = Regex::new(r"(?m)^export\s*\{([^}]*)\}").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrontmatterKind {
+  /// `---` fenced YAML
+  Yaml,
+  /// `+++` fenced TOML
+  Toml,
+}
+
+/// A frontmatter block found at the very start of a document.
+#[derive(Debug, PartialEq)]
+pub struct Frontmatter {
+  pub kind: FrontmatterKind,
+  /// Parsed data, always an object.
+  pub data: Map<String, Value>,
+  /// Number of lines the block spans, fences included.
+  pub lines: usize,
+  /// Byte length of the block, including the line break after the closing fence.
+  pub len: usize,
+}
+
+/// Finds and parses the frontmatter at the start of `content`.
+pub fn parse_frontmatter(
+  resolved_path: &str,
+  content: &str,
+) -> Result<Option<Frontmatter>, CompilationError> {
+  let (kind, fence) = if content.starts_with("---") {
+    (FrontmatterKind::Yaml, "---")
+  } else if content.starts_with("+++") {
+    (FrontmatterKind::Toml, "+++")
+  } else {
+    return Ok(None);
+  };
+
+  let mut offset = 0;
+  let mut body_start = None;
+  for (index, line) in content.split_inclusive('\n').enumerate() {
+    let is_fence = line.trim_end() == fence;
+    match body_start {
+      None if !is_fence => return Ok(None),
+      None => body_start = Some(offset + line.len()),
+      Some(start) if is_fence => {
+        let source = &content[start..offset];
+        let data = match kind {
+          FrontmatterKind::Yaml => parse_yaml(source),
+          FrontmatterKind::Toml => parse_toml(source),
+        }
+        .map_err(|reason| CompilationError::ParseError {
+          resolved_path: resolved_path.to_string(),
+          msg: format!("Failed to parse the frontmatter of {resolved_path}: {reason}"),
+        })?;
+        return Ok(Some(Frontmatter {
+          kind,
+          data,
+          lines: index + 1,
+          len: offset + line.len(),
+        }));
+      }
+      Some(_) => {}
+    }
+    offset += line.len();
+  }
+  // an unclosed fence is a thematic break, not frontmatter
+  Ok(None)
+}
+
+fn parse_yaml(source: &str) -> Result<Map<String, Value>, String> {
+  if source.trim().is_empty() {
+    return Ok(Map::new());
+  }
+  match serde_yaml::from_str::<Value>(source).map_err(|e| e.to_string())? {
+    Value::Object(data) => Ok(data),
+    Value::Null => Ok(Map::new()),
+    _ => Err("the frontmatter must be a mapping".to_string()),
+  }
+}
+
+fn parse_toml(source: &str) -> Result<Map<String, Value>, String> {
+  let table = source
+    .parse::<toml::Table>()
+    .map_err(|e| e.message().to_string())?;
+  Ok(
+    table
+      .into_iter()
+      .map(|(key, value)| (key, toml_to_json(value)))
+      .collect(),
+  )
+}
+
+/// TOML dates serialize to a private struct through serde, keep them as strings instead.
+fn toml_to_json(value: toml::Value) -> Value {
+  match value {
+    toml::Value::String(s) => Value::String(s),
+    toml::Value::Integer(i) => Value::from(i),
+    toml::Value::Float(f) => Value::from(f),
+    toml::Value::Boolean(b) => Value::Bool(b),
+    toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+    toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+    toml::Value::Table(table) => Value::Object(
+      table
+        .into_iter()
+        .map(|(key, value)| (key, toml_to_json(value)))
+        .collect(),
+    ),
+  }
+}
+
+/// Whether `name` can be declared as is in an ES module, reserved words such
+/// as `with` or `public` excluded
+pub fn is_valid_identifier(name: &str) -> bool {
+  Ident::verify_symbol(name).is_ok()
+}
+
+/// Names exported by the ESM of `content`, e.g. `export const title = ...`
+fn exported_names(content: &str) -> HashSet<&str> {
+  let declared = EXPORT_DECL
+    .captures_iter(content)
+    .filter_map(|captures| captures.get(1))
+    .map(|name| name.as_str());
+  let listed = EXPORT_LIST
+    .captures_iter(content)
+    .filter_map(|captures| captures.get(1))
+    .flat_map(|list| list.as_str().split(','))
+    .filter_map(|specifier| specifier.split_whitespace().last());
+  declared.chain(listed).collect()
+}
+
+/// Replaces the frontmatter with an ESM `export const frontmatter = {...}` statement.
+///
+/// The statement sits on the first line of the block and the remaining lines are
+/// left empty, so MDX still reports the positions of the original document.
+/// With `variables`, fields that are valid identifiers are also exported by name
+/// so the body can use `{title}` instead of `{frontmatter.title}`. Names the
+/// compiled module or the document already declares are left out.
+pub fn replace_frontmatter(content: &str, frontmatter: &Frontmatter, variables: bool) -> String {
+  let mut esm = format!(
+    "export const frontmatter = {};",
+    serde_json::to_string(&frontmatter.data).unwrap()
+  );
+  if variables {
+    let exported = exported_names(&content[frontmatter.len..]);
+    let names = frontmatter
+      .data
+      .keys()
+      .filter(|key| {
+        is_valid_identifier(key)
+          && !key.starts_with('_')
+          && !MDX_NAMES.contains(&key.as_str())
+          && !exported.contains(key.as_str())
+      })
+      .map(String::as_str)
+      .collect::<Vec<_>>();
+    if !names.is_empty() {
+      esm.push_str(&format!(
+        " export const {{ {} }} = frontmatter;",
+        names.join(", ")
+      ));
+    }
+  }
+  // the block spans at least two lines, so a blank line always ends the ESM block
+  format!(
+    "{esm}{}{}",
+    "\n".repeat(frontmatter.lines),
+    &content[frontmatter.len..]
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_yaml_frontmatter() {
+    let content = "---\ntitle: Hello\ntags: [a, b]\nmy-key: 1\n---\n# {title}\n";
+    let frontmatter = parse_frontmatter("post.mdx", content).unwrap().unwrap();
+    assert_eq!(frontmatter.kind, FrontmatterKind::Yaml);
+    assert_eq!(frontmatter.lines, 5);
+    assert_eq!(
+      replace_frontmatter(content, &frontmatter, true),
+      "export const frontmatter = {\"title\":\"Hello\",\"tags\":[\"a\",\"b\"],\"my-key\":1}; \
+       export const { title, tags } = frontmatter;\n\n\n\n\n# {title}\n"
+    );
+  }
+
+  #[test]
+  fn test_reserved_keys() {
+    let content = "---\nwith: a\npublic: true\nyield: 1\nauthor: b\n---\n";
+    let frontmatter = parse_frontmatter("post.mdx", content).unwrap().unwrap();
+    assert!(replace_frontmatter(content, &frontmatter, true)
+      .contains(" export const { author } = frontmatter;"));
+    assert!(!is_valid_identifier("private"));
+    assert!(!is_valid_identifier("1st"));
+    assert!(is_valid_identifier("$title"));
+  }
+
+  #[test]
+  fn test_generated_and_exported_keys() {
+    let content =
+      "---\ntoc: [a]\n_components: 1\nMDXLayout: 2\ntitle: a\nauthor: b\ndate: c\n---\n\n\
+                   export const title = 'b';\nexport { x as date };\n";
+    let frontmatter = parse_frontmatter("post.mdx", content).unwrap().unwrap();
+    assert!(replace_frontmatter(content, &frontmatter, true)
+      .contains(" export const { author } = frontmatter;"));
+  }
+
+  #[test]
+  fn test_toml_frontmatter() {
+    let content = "+++\ntitle = \"Hello\"\ndate = 2024-01-02\n+++\n\nbody";
+    let frontmatter = parse_frontmatter("post.mdx", content).unwrap().unwrap();
+    assert_eq!(frontmatter.kind, FrontmatterKind::Toml);
+    assert_eq!(
+      Value::Object(frontmatter.data.clone()),
+      serde_json::json!({ "title": "Hello", "date": "2024-01-02" })
+    );
+    assert_eq!(
+      replace_frontmatter(content, &frontmatter, false),
+      "export const frontmatter = {\"title\":\"Hello\",\"date\":\"2024-01-02\"};\n\n\n\n\nbody"
+    );
+  }
+
+  #[test]
+  fn test_no_frontmatter() {
+    assert_eq!(parse_frontmatter("a.mdx", "# Title\n---\n").unwrap(), None);
+    assert_eq!(
+      parse_frontmatter("a.mdx", "---\n\nno closing fence").unwrap(),
+      None
+    );
+    assert!(parse_frontmatter("a.mdx", "---\n- a\n---\n").is_err());
+  }
+}
@@ -0,0 +1,569 @@
+use std::{
+  collections::HashSet,
+  fmt, fs,
+  path::{Path, PathBuf},
+};
+
+use farmfe_core::serde_json;
+use lazy_static::lazy_static;
+use markdown::mdast::{Code, Node};
+use regex::Regex;
+use serde::Deserialize;
+use syntect::{
+  easy::HighlightLines,
+  highlighting::{Color, FontStyle, Style, Theme, ThemeSet},
+  html::{css_for_theme_with_class_style, ClassStyle},
+  parsing::{BasicScopeStackOp, ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet},
+  util::LinesWithEndings,
+};
+
+lazy_static! {
+  static ref META_LINES: Regex = Regex::new(r"\{([\d,\s-]+)\}").unwrap();
+  static ref META_TITLE: Regex = Regex::new(r#"title=(?:"([^"]*)"|'([^']*)')"#).unwrap();
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum HighlightMode {
+  /// `style={{ color }}` on every token, colors come from the theme
+  #[default]
+  Inline,
+  /// TextMate scopes as class names, e.g. `keyword control js`
+  Classes,
+}
+
+/// `highlight` option
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum HighlightConfig {
+  Enabled(bool),
+  Options(HighlightOptions),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HighlightOptions {
+  /// Name of a built-in theme, or of a `.tmTheme` in `themes`
+  pub theme: String,
+  pub mode: HighlightMode,
+  /// Prefix of the class names in `classes` mode
+  pub class_prefix: String,
+  /// Folder of extra `.sublime-syntax` grammars, relative to the root.
+  /// syntect does not read TextMate `.tmLanguage` grammars.
+  pub syntaxes: Option<String>,
+  /// Folder of extra `.tmTheme` themes, relative to the root
+  pub themes: Option<String>,
+  /// In `classes` mode, write the stylesheet of `theme` to this file
+  pub css: Option<String>,
+}
+
+impl Default for HighlightOptions {
+  fn default() -> Self {
+    Self {
+      theme: "InspiredGitHub".to_string(),
+      mode: HighlightMode::Inline,
+      class_prefix: String::new(),
+      syntaxes: None,
+      themes: None,
+      css: None,
+    }
+  }
+}
+
+impl HighlightConfig {
+  pub fn options(&self) -> Option<HighlightOptions> {
+    match self {
+      HighlightConfig::Enabled(true) => Some(HighlightOptions::default()),
+      HighlightConfig::Enabled(false) => None,
+      HighlightConfig::Options(options) => Some(options.clone()),
+    }
+  }
+}
+
+/// Grammars and theme, loaded once when the plugin is created.
+pub struct Highlighter {
+  syntaxes: SyntaxSet,
+  theme: Theme,
+  mode: HighlightMode,
+  class_prefix: String,
+  /// where the stylesheet of `theme` is written in `classes` mode
+  css: Option<PathBuf>,
+}
+
+impl fmt::Debug for Highlighter {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Highlighter")
+      .field("theme", &self.theme.name)
+      .field("mode", &self.mode)
+      .finish()
+  }
+}
+
+impl Highlighter {
+  pub fn new(options: &HighlightOptions, root: &str) -> Result<Self, String> {
+    let root = Path::new(root);
+    let syntaxes = match &options.syntaxes {
+      Some(folder) => {
+        let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+        builder
+          .add_from_folder(root.join(folder), true)
+          .map_err(|e| format!("Failed to load the grammars in `{folder}`: {e}"))?;
+        builder.build()
+      }
+      None => SyntaxSet::load_defaults_newlines(),
+    };
+    let mut themes = ThemeSet::load_defaults();
+    if let Some(folder) = &options.themes {
+      themes
+        .add_from_folder(root.join(folder))
+        .map_err(|e| format!("Failed to load the themes in `{folder}`: {e}"))?;
+    }
+    let theme = themes.themes.remove(&options.theme).ok_or_else(|| {
+      let mut names = themes.themes.keys().cloned().collect::<Vec<_>>();
+      names.sort();
+      format!(
+        "Unknown highlight theme `{}`, available themes: {}",
+        options.theme,
+        names.join(", ")
+      )
+    })?;
+
+    Ok(Self {
+      syntaxes,
+      theme,
+      mode: options.mode,
+      class_prefix: options.class_prefix.clone(),
+      css: options
+        .css
+        .as_ref()
+        .filter(|_| options.mode == HighlightMode::Classes)
+        .map(|css| root.join(css)),
+    })
+  }
+
+  /// Writes the stylesheet of the theme in `classes` mode, unless it is up to date
+  pub fn write_css(&self) -> Result<(), String> {
+    let Some(path) = &self.css else {
+      return Ok(());
+    };
+    let stylesheet = css_for_theme_with_class_style(&self.theme, ClassStyle::Spaced)
+      .map(|css| prefix_css_classes(&css, &self.class_prefix))
+      .map_err(|e| e.to_string())?;
+    if fs::read_to_string(path).ok().as_deref() != Some(stylesheet.as_str()) {
+      fs::write(path, stylesheet)
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    }
+    Ok(())
+  }
+
+  /// Replaces the fenced code blocks of `content` with pre-tokenized JSX.
+  ///
+  /// Each line of a block becomes one line of JSX, so the lines after it keep
+  /// their positions in MDX diagnostics.
+  pub fn highlight_code_blocks(&self, content: &str, mdast: &Node) -> String {
+    let mut blocks = vec![];
+    collect_code(mdast, &mut blocks);
+
+    let mut result = content.to_string();
+    // from the end, so earlier offsets stay valid
+    for code in blocks.into_iter().rev() {
+      let Some(position) = &code.position else {
+        continue;
+      };
+      let source = &content[position.start.offset..position.end.offset];
+      let prefix_width = position.start.column - 1;
+      let jsx = self.code_block_to_jsx(code, source.lines().count());
+      let jsx = jsx
+        .iter()
+        .zip(source.lines())
+        .enumerate()
+        .map(|(index, (jsx, line))| {
+          if index == 0 {
+            return jsx.clone();
+          }
+          // keep the `> ` or list indentation the block is nested in
+          let prefix = line.get(..prefix_width).unwrap_or(line);
+          format!("{prefix}{jsx}")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+      result.replace_range(position.start.offset..position.end.offset, &jsx);
+    }
+    result
+  }
+
+  /// Returns exactly `line_count` lines of JSX for a block that spans `line_count` lines.
+  fn code_block_to_jsx(&self, code: &Code, line_count: usize) -> Vec<String> {
+    let meta = CodeMeta::parse(
+      code.meta.as_deref().unwrap_or_default(),
+      code.value.lines().count(),
+    );
+    let syntax = self.find_syntax(code.lang.as_deref());
+    let language = code.lang.as_deref().unwrap_or("text");
+
+    let tokens = self.highlight_lines(&code.value, syntax);
+    let last = tokens.len().saturating_sub(1);
+    let lines = tokens
+      .into_iter()
+      .enumerate()
+      .map(|(index, tokens)| {
+        let class_name = if meta.highlighted_lines.contains(&(index + 1)) {
+          "line highlighted"
+        } else {
+          "line"
+        };
+        // inside the span, MDX would turn a break after an expression into text
+        let newline = if index == last { "" } else { "{\"\\n\"}" };
+        format!("<span className=\"{class_name}\">{tokens}{newline}</span>")
+      })
+      .collect::<Vec<_>>();
+
+    let pre_style = match self.mode {
+      HighlightMode::Inline => {
+        let settings = &self.theme.settings;
+        let mut style = vec![];
+        if let Some(background) = settings.background {
+          style.push(format!("backgroundColor: {}", js_string(&hex(background))));
+        }
+        if let Some(foreground) = settings.foreground {
+          style.push(format!("color: {}", js_string(&hex(foreground))));
+        }
+        format!(" style={{{{{}}}}}", style.join(", "))
+      }
+      HighlightMode::Classes => String::new(),
+    };
+    let mut open = format!(
+      "<pre className=\"{}code\" data-language={}{pre_style}><code className={}>",
+      self.class_prefix,
+      js_string(language),
+      js_string(&format!("language-{language}"))
+    );
+    let mut close = "</code></pre>".to_string();
+    if let Some(title) = &meta.title {
+      open = format!(
+        "<figure data-code-block=\"\"><figcaption data-code-title=\"\">{{{}}}</figcaption>{open}",
+        js_string(title)
+      );
+      close.push_str("</figure>");
+    }
+
+    // a closed fence spans the content plus the two fence lines
+    let closed = line_count >= lines.len() + 2;
+    let mut jsx = vec![open];
+    jsx.extend(lines);
+    if closed {
+      jsx.push(close);
+    } else {
+      jsx.last_mut().unwrap().push_str(&close);
+    }
+    jsx.resize(line_count.max(1), String::new());
+    jsx
+  }
+
+  fn find_syntax(&self, lang: Option<&str>) -> &SyntaxReference {
+    lang
+      .and_then(|lang| self.syntaxes.find_syntax_by_token(lang))
+      .unwrap_or_else(|| self.syntaxes.find_syntax_plain_text())
+  }
+
+  /// The tokens of each line as JSX children
+  fn highlight_lines(&self, value: &str, syntax: &SyntaxReference) -> Vec<String> {
+    if value.is_empty() {
+      return vec![];
+    }
+    match self.mode {
+      HighlightMode::Inline => {
+        let mut highlighter = HighlightLines::new(syntax, &self.theme);
+        LinesWithEndings::from(value)
+          .map(|line| {
+            let tokens = highlighter
+              .highlight_line(line, &self.syntaxes)
+              .unwrap_or_else(|_| vec![(Style::default(), line)]);
+            // adjacent tokens with the same style share a span
+            let mut merged: Vec<(Vec<String>, String)> = vec![];
+            for (style, text) in tokens {
+              let properties = self.style_properties(style);
+              match merged.last_mut() {
+                Some((last, merged_text)) if *last == properties => merged_text.push_str(text),
+                _ => merged.push((properties, text.to_string())),
+              }
+            }
+            merged
+              .into_iter()
+              .map(|(properties, text)| styled_token(&properties, text.trim_end_matches('\n')))
+              .collect()
+          })
+          .collect()
+      }
+      HighlightMode::Classes => {
+        let mut state = ParseState::new(syntax);
+        let mut stack = ScopeStack::new();
+        LinesWithEndings::from(value)
+          .map(|line| {
+            let ops = state.parse_line(line, &self.syntaxes).unwrap_or_default();
+            self.classed_line(line.trim_end_matches('\n'), &ops, &mut stack)
+          })
+          .collect()
+      }
+    }
+  }
+
+  fn style_properties(&self, style: Style) -> Vec<String> {
+    let mut properties = vec![];
+    if Some(style.foreground) != self.theme.settings.foreground {
+      properties.push(format!("color: {}", js_string(&hex(style.foreground))));
+    }
+    if style.font_style.contains(FontStyle::BOLD) {
+      properties.push("fontWeight: \"bold\"".to_string());
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+      properties.push("fontStyle: \"italic\"".to_string());
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+      properties.push("textDecoration: \"underline\"".to_string());
+    }
+    properties
+  }
+
+  /// Nested spans per scope, like syntect's classed HTML. Scopes still open at
+  /// the end of a line are closed and reopened on the next one.
+  fn classed_line(
+    &self,
+    line: &str,
+    ops: &[(usize, syntect::parsing::ScopeStackOp)],
+    stack: &mut ScopeStack,
+  ) -> String {
+    let mut jsx = String::new();
+    let mut open = 0;
+    for scope in stack.as_slice() {
+      jsx.push_str(&self.open_scope(*scope));
+      open += 1;
+    }
+    let mut cursor = 0;
+    for (index, op) in ops {
+      let index = (*index).min(line.len());
+      if index > cursor {
+        jsx.push_str(&format!("{{{}}}", js_string(&line[cursor..index])));
+        cursor = index;
+      }
+      let _ = stack.apply_with_hook(op, |op, _| match op {
+        BasicScopeStackOp::Push(scope) => {
+          jsx.push_str(&self.open_scope(scope));
+          open += 1;
+        }
+        BasicScopeStackOp::Pop => {
+          if open > 0 {
+            jsx.push_str("</span>");
+            open -= 1;
+          }
+        }
+      });
+    }
+    if cursor < line.len() {
+      jsx.push_str(&format!("{{{}}}", js_string(&line[cursor..])));
+    }
+    jsx.push_str(&"</span>".repeat(open));
+    jsx
+  }
+
+  fn open_scope(&self, scope: Scope) -> String {
+    let classes = scope
+      .build_string()
+      .split('.')
+      .map(|atom| format!("{}{atom}", self.class_prefix))
+      .collect::<Vec<_>>()
+      .join(" ");
+    format!("<span className={}>", js_string(&classes))
+  }
+}
+
+/// Highlighted lines and title from the meta string, e.g. `{1,3-4} title="app.js"`.
+/// Ranges are clamped to the `line_count` lines of the block.
+#[derive(Debug, Default, PartialEq)]
+pub struct CodeMeta {
+  pub highlighted_lines: HashSet<usize>,
+  pub title: Option<String>,
+}
+
+impl CodeMeta {
+  pub fn parse(meta: &str, line_count: usize) -> Self {
+    let mut highlighted_lines = HashSet::new();
+    if let Some(ranges) = META_LINES.captures(meta) {
+      for range in ranges[1].split(',') {
+        let range = range.trim();
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        if let (Ok(start), Ok(end)) = (start.trim().parse(), end.trim().parse::<usize>()) {
+          highlighted_lines.extend(start..=end.min(line_count));
+        }
+      }
+    }
+    let title = META_TITLE.captures(meta).and_then(|captures| {
+      captures
+        .get(1)
+        .or_else(|| captures.get(2))
+        .map(|title| title.as_str().to_string())
+    });
+    Self {
+      highlighted_lines,
+      title,
+    }
+  }
+}
+
+fn collect_code<'a>(node: &'a Node, blocks: &mut Vec<&'a Code>) {
+  if let Node::Code(code) = node {
+    blocks.push(code);
+  }
+  if let Some(children) = node.children() {
+    for child in children {
+      collect_code(child, blocks);
+    }
+  }
+}
+
+fn styled_token(properties: &[String], text: &str) -> String {
+  if text.is_empty() {
+    return String::new();
+  }
+  if properties.is_empty() {
+    return format!("{{{}}}", js_string(text));
+  }
+  format!(
+    "<span style={{{{{}}}}}>{{{}}}</span>",
+    properties.join(", "),
+    js_string(text)
+  )
+}
+
+fn hex(color: Color) -> String {
+  if color.a == 0xff {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+  } else {
+    format!(
+      "#{:02x}{:02x}{:02x}{:02x}",
+      color.r, color.g, color.b, color.a
+    )
+  }
+}
+
+/// Prefixes the class selectors of a stylesheet generated without a prefix.
+/// syntect only takes a `'static` prefix, while this one comes from the options.
+fn prefix_css_classes(css: &str, prefix: &str) -> String {
+  let prefix = prefix
+    .char_indices()
+    .map(|(index, c)| {
+      if c.is_ascii_alphabetic() || c == '-' || c == '_' || (index > 0 && c.is_ascii_digit()) {
+        c.to_string()
+      } else {
+        format!("\\{:x} ", c as u32)
+      }
+    })
+    .collect::<String>();
+  css
+    .split_inclusive('\n')
+    .map(|line| {
+      // only selector lines open a block, the theme name sits in a comment
+      if line.trim_end().ends_with('{') {
+        line.replace('.', &format!(".{prefix}"))
+      } else {
+        line.to_string()
+      }
+    })
+    .collect()
+}
+
+fn js_string(value: &str) -> String {
+  serde_json::to_string(value).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mdast::to_mdast;
+
+  #[test]
+  fn test_code_meta() {
+    assert_eq!(
+      CodeMeta::parse(r#"{1,3-4} title="app.js""#, 5),
+      CodeMeta {
+        highlighted_lines: HashSet::from([1, 3, 4]),
+        title: Some("app.js".to_string()),
+      }
+    );
+    assert_eq!(CodeMeta::parse("title='x'", 1).title, Some("x".to_string()));
+    assert_eq!(CodeMeta::parse("", 1), CodeMeta::default());
+    assert_eq!(
+      CodeMeta::parse("{2-1000000000}", 3).highlighted_lines,
+      HashSet::from([2, 3])
+    );
+  }
+
+  #[test]
+  fn test_highlight_code_blocks() {
+    let highlighter = Highlighter::new(&HighlightOptions::default(), ".").unwrap();
+    let content = "> ```js {2} title=\"a.js\"\n> let a = 1;\n> a;\n> ```\n\nafter\n";
+    let mdast = to_mdast(content, &Default::default()).unwrap();
+    let result = highlighter.highlight_code_blocks(content, &mdast);
+    let lines = result.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 6);
+    assert!(lines[0].starts_with(
+      "> <figure data-code-block=\"\"><figcaption data-code-title=\"\">{\"a.js\"}</figcaption>\
+       <pre className=\"code\" data-language=\"js\" style={{backgroundColor: \"#ffffff\", color: \"#323232\"}}>\
+       <code className=\"language-js\">"
+    ));
+    assert!(lines[1].starts_with("> <span className=\"line\"><span style={{color: "));
+    assert!(lines[1].ends_with("{\";\"}{\"\\n\"}</span>"));
+    assert!(lines[2].starts_with("> <span className=\"line highlighted\">"));
+    assert_eq!(lines[3], "> </code></pre></figure>");
+    assert_eq!(&lines[4..], ["", "after"]);
+
+    let code = mdxjs::compile(
+      &result,
+      &mdxjs::Options {
+        jsx: true,
+        ..Default::default()
+      },
+    )
+    .unwrap();
+    assert!(code.contains("<span className=\"line highlighted\">"));
+    assert!(!code.contains("_components.p>{\"let"));
+    assert!(!code.contains("{\"\\n\"}{\"\\n\"}"));
+  }
+
+  #[test]
+  fn test_classes_mode() {
+    let highlighter = Highlighter::new(
+      &HighlightOptions {
+        mode: HighlightMode::Classes,
+        class_prefix: "hl-".to_string(),
+        ..Default::default()
+      },
+      ".",
+    )
+    .unwrap();
+    let content = "```rust\nfn main() {}\n```\n";
+    let mdast = to_mdast(content, &Default::default()).unwrap();
+    let result = highlighter.highlight_code_blocks(content, &mdast);
+    assert!(result.starts_with(
+      "<pre className=\"hl-code\" data-language=\"rust\"><code className=\"language-rust\">\n\
+       <span className=\"line\"><span className=\"hl-source hl-rust\">"
+    ));
+    assert!(
+      result.contains("<span className=\"hl-storage hl-type hl-function hl-rust\">{\"fn\"}</span>")
+    );
+    assert!(result.ends_with("</span></span>\n</code></pre>\n"));
+  }
+
+  #[test]
+  fn test_prefix_css_classes() {
+    let theme = ThemeSet::load_defaults()
+      .themes
+      .remove("InspiredGitHub")
+      .unwrap();
+    let css = css_for_theme_with_class_style(&theme, ClassStyle::Spaced).unwrap();
+    assert_eq!(
+      prefix_css_classes(&css, "hl-"),
+      css_for_theme_with_class_style(&theme, ClassStyle::SpacedPrefixed { prefix: "hl-" }).unwrap()
+    );
+  }
+}
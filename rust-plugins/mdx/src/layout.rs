@@ -0,0 +1,265 @@
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+};
+
+use farmfe_core::{
+  error::CompilationError,
+  serde_json::{self, Map, Value},
+};
+use lazy_static::lazy_static;
+use markdown::mdast::Node;
+use regex::Regex;
+
+lazy_static! {
+  static ref DEFAULT_EXPORT: Regex =
+    Regex::new(r"(^|[\s;])export\s+default\b|\bexport\s*\{[^}]*\bdefault\b").unwrap();
+}
+
+/// Layouts of the `layouts` option, and the default layout of the directories
+/// of `defaultLayouts`
+#[derive(Debug, Default)]
+pub struct Layouts {
+  /// Name to import source
+  modules: HashMap<String, String>,
+  /// Directory to layout name, deepest first
+  defaults: Vec<(PathBuf, String)>,
+}
+
+impl Layouts {
+  pub fn new(
+    layouts: &HashMap<String, String>,
+    defaults: &HashMap<String, String>,
+    root: &str,
+  ) -> Result<Self, String> {
+    let modules = layouts
+      .iter()
+      .map(|(name, module)| {
+        // `./layouts/Post.tsx` is relative to the root, `@/layouts/Post` is left to the resolver
+        let source = if module.starts_with("./") || module.starts_with("../") {
+          // collecting the components drops the `.`
+          Path::new(root)
+            .join(module)
+            .components()
+            .collect::<PathBuf>()
+            .to_string_lossy()
+            .replace('\\', "/")
+        } else {
+          module.clone()
+        };
+        (name.clone(), source)
+      })
+      .collect::<HashMap<_, _>>();
+    let mut directories = vec![];
+    for (dir, name) in defaults {
+      if !modules.contains_key(name) {
+        return Err(format!(
+          "`defaultLayouts` sets `{dir}` to the unknown layout `{name}`, add it to `layouts`"
+        ));
+      }
+      directories.push((Path::new(root).join(dir), name.clone()));
+    }
+    directories.sort_by_key(|(dir, _)| std::cmp::Reverse(dir.components().count()));
+    Ok(Self {
+      modules,
+      defaults: directories,
+    })
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.modules.is_empty()
+  }
+
+  /// Import source of the layout of a page: the `layout` field of the
+  /// frontmatter, `false` for none, or the default of its directory. Pages
+  /// exporting their own layout keep it.
+  pub fn resolve(
+    &self,
+    resolved_path: &str,
+    frontmatter: Option<&Map<String, Value>>,
+    mdast: &Node,
+  ) -> Result<Option<&str>, CompilationError> {
+    let error = |msg: String| CompilationError::TransformError {
+      resolved_path: resolved_path.to_string(),
+      msg,
+    };
+    let name = match frontmatter.and_then(|data| data.get("layout")) {
+      Some(Value::Bool(false)) | Some(Value::Null) => return Ok(None),
+      Some(Value::String(name)) => {
+        if has_default_export(mdast) {
+          return Err(error(format!(
+            "`layout: {name}` is set in the frontmatter, but the file exports its own layout"
+          )));
+        }
+        name
+      }
+      Some(value) => {
+        return Err(error(format!(
+          "`layout` must be the name of a layout or `false`, got `{}`",
+          serde_json::to_string(value).unwrap()
+        )))
+      }
+      None => {
+        let dir = self
+          .defaults
+          .iter()
+          .find(|(dir, _)| Path::new(resolved_path).starts_with(dir));
+        match dir {
+          Some((_, name)) if !has_default_export(mdast) => name,
+          _ => return Ok(None),
+        }
+      }
+    };
+    match self.modules.get(name) {
+      Some(source) => Ok(Some(source)),
+      None => {
+        let mut names = self.modules.keys().map(String::as_str).collect::<Vec<_>>();
+        names.sort();
+        Err(error(format!(
+          "Unknown layout `{name}`, expected one of: {}",
+          names.join(", ")
+        )))
+      }
+    }
+  }
+}
+
+/// Appends an ESM block exporting the layout, mdxjs then wraps the content in
+/// it. The block goes last so the positions of the document do not move.
+pub fn append_layout(
+  content: &str,
+  mdast: &Node,
+  source: &str,
+  frontmatter: bool,
+  toc: bool,
+) -> String {
+  let frontmatter = if frontmatter { "frontmatter" } else { "{}" };
+  let toc = if toc { "toc" } else { "[]" };
+  let mut content = content.to_string();
+  // a fence that is not closed runs to the end of the document
+  if let Some(fence) = unclosed_fence(&content, mdast) {
+    content.push_str(&format!("\n{fence}"));
+  }
+  content.push_str(&format!(
+    "\n\nimport _MDXLayout from {};\n\
+     export default function _MDXLayoutWithProps(props) {{ \
+     return <_MDXLayout {{...props}} frontmatter={{{frontmatter}}} toc={{{toc}}} />; }}\n",
+    serde_json::to_string(source).unwrap()
+  ));
+  content
+}
+
+fn has_default_export(mdast: &Node) -> bool {
+  mdast
+    .children()
+    .into_iter()
+    .flatten()
+    .any(|node| match node {
+      Node::MdxjsEsm(esm) => DEFAULT_EXPORT.is_match(&esm.value),
+      _ => false,
+    })
+}
+
+/// The opening fence of a code block that ends the document without being closed
+fn unclosed_fence(content: &str, mdast: &Node) -> Option<String> {
+  let Some(Node::Code(code)) = mdast.children()?.last() else {
+    return None;
+  };
+  let position = code.position.as_ref()?;
+  let block = &content[position.start.offset..position.end.offset];
+  let opening = block.lines().next()?.trim_start();
+  let marker = opening.chars().next().filter(|c| *c == '`' || *c == '~')?;
+  let fence = opening
+    .chars()
+    .take_while(|c| *c == marker)
+    .collect::<String>();
+  let closed = block.lines().count() > 1
+    && block.lines().last().is_some_and(|line| {
+      let line = line.trim();
+      line.len() >= fence.len() && line.chars().all(|c| c == marker)
+    });
+  (!closed).then_some(fence)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mdast::to_mdast;
+  use mdxjs::MdxParseOptions;
+
+  fn layouts() -> Layouts {
+    Layouts::new(
+      &HashMap::from([
+        ("post".to_string(), "./layouts/Post.jsx".to_string()),
+        ("doc".to_string(), "@/layouts/Doc".to_string()),
+      ]),
+      &HashMap::from([
+        ("src".to_string(), "doc".to_string()),
+        ("src/posts".to_string(), "post".to_string()),
+      ]),
+      "/root",
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn test_resolve() {
+    let layouts = layouts();
+    let parse = MdxParseOptions::default();
+    let mdast = to_mdast("# Hi\n", &parse).unwrap();
+    let frontmatter = |value: Value| Map::from_iter([("layout".to_string(), value)]);
+    let resolve =
+      |path: &str, data: Option<&Map<String, Value>>| layouts.resolve(path, data, &mdast).unwrap();
+    assert_eq!(
+      resolve("/root/src/posts/a.mdx", None),
+      Some("/root/layouts/Post.jsx")
+    );
+    assert_eq!(
+      resolve("/root/src/guide/a.mdx", None),
+      Some("@/layouts/Doc")
+    );
+    assert_eq!(resolve("/root/docs/a.mdx", None), None);
+    let data = frontmatter(Value::String("doc".to_string()));
+    assert_eq!(
+      resolve("/root/src/posts/a.mdx", Some(&data)),
+      Some("@/layouts/Doc")
+    );
+    let data = frontmatter(Value::Bool(false));
+    assert_eq!(resolve("/root/src/posts/a.mdx", Some(&data)), None);
+
+    let data = frontmatter(Value::String("page".to_string()));
+    assert!(layouts.resolve("/root/a.mdx", Some(&data), &mdast).is_err());
+    let mdast = to_mdast("export default function Layout() {}\n\n# Hi\n", &parse).unwrap();
+    assert_eq!(
+      layouts.resolve("/root/src/a.mdx", None, &mdast).unwrap(),
+      None
+    );
+    let data = frontmatter(Value::String("doc".to_string()));
+    assert!(layouts.resolve("/root/a.mdx", Some(&data), &mdast).is_err());
+
+    assert!(Layouts::new(
+      &HashMap::new(),
+      &HashMap::from([("src".to_string(), "post".to_string())]),
+      "/root"
+    )
+    .is_err());
+  }
+
+  #[test]
+  fn test_append_layout() {
+    let parse = MdxParseOptions::default();
+    let content = "# Hi\n\n```js\nlet a;\n";
+    let mdast = to_mdast(content, &parse).unwrap();
+    assert_eq!(
+      append_layout(content, &mdast, "/root/Post.jsx", true, false),
+      "# Hi\n\n```js\nlet a;\n\n```\n\n\
+       import _MDXLayout from \"/root/Post.jsx\";\n\
+       export default function _MDXLayoutWithProps(props) { \
+       return <_MDXLayout {...props} frontmatter={frontmatter} toc={[]} />; }\n"
+    );
+    let content = "~~~\nlet a;\n~~~\n";
+    let mdast = to_mdast(content, &parse).unwrap();
+    assert!(append_layout(content, &mdast, "./Post", false, true)
+      .starts_with("~~~\nlet a;\n~~~\n\n\nimport _MDXLayout from \"./Post\";"));
+  }
+}
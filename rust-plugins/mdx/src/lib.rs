@@ -1,18 +1,51 @@
 #![deny(clippy::all)]
+mod collection;
+mod emoji;
+mod error;
+mod framework;
+mod frontmatter;
+mod highlight;
+mod layout;
+mod links;
+mod markdown;
+mod math;
+mod mdast;
+mod rewrite;
+mod toc;
+mod transforms;
+
+use crate::markdown::{MarkdownConfig, MarkdownRenderer};
+use collection::{write_dts, Collection, CollectionOptions, DtsConfig, COLLECTION_PREFIX};
+use error::message_to_compilation_error;
+use farmfe_core::error::CompilationError;
 use farmfe_core::module::ModuleType;
 use farmfe_core::serde_json;
+use framework::Framework;
+use frontmatter::{parse_frontmatter, replace_frontmatter};
+use highlight::{HighlightConfig, Highlighter};
+use layout::{append_layout, Layouts};
+use links::{check_links, CheckLinksConfig, LinkLevel};
+use mdast::to_mdast;
 use mdxjs::compile;
 use mdxjs::JsxRuntime;
 use mdxjs::MdxParseOptions;
 use mdxjs::Options;
 use regex::Regex;
+use rewrite::{collect_elements, rewrite_module, MdxRewriter};
 use serde::Deserialize;
+use toc::{collect_toc, stringify_toc, TocConfig, TocOptions};
+use transforms::{Transform, Transforms};
 
 use farmfe_core::{config::Config, plugin::Plugin};
 
-use farmfe_core::plugin::PluginLoadHookResult;
+use farmfe_core::module::ModuleId;
+use farmfe_core::plugin::{
+  PluginLoadHookResult, PluginResolveHookResult, PluginUpdateModulesHookParam, UpdateType,
+};
 use farmfe_macro_plugin::farm_plugin;
+use std::collections::HashMap;
 use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
 
 #[farm_plugin]
 #[derive(Debug, Default)]
@@ -20,6 +53,20 @@ pub struct FarmPluginMdx {
   mdx_options: Options,
   include: Option<String>,
   exclude: Option<String>,
+  frontmatter_variables: bool,
+  toc: Option<TocOptions>,
+  highlighter: Option<Highlighter>,
+  markdown: Option<MarkdownRenderer>,
+  resolve_images: bool,
+  check_links: Option<LinkLevel>,
+  framework: Framework,
+  collections: HashMap<String, Collection>,
+  /// where the declarations of the collections are written
+  collections_dts: Option<PathBuf>,
+  layouts: Layouts,
+  transforms: Transforms,
+  /// invalid options, reported when the build starts
+  config_errors: Vec<String>,
 }
 #[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -36,21 +83,87 @@ struct FarmPluginMdxOptions {
   jsx_import_source: Option<String>,
   filepath: Option<String>,
   parse: Option<MdxParseOptions>,
+  /// export frontmatter fields as variables usable in the MDX body
+  frontmatter_variables: Option<bool>,
+  /// `export const toc` and heading ids, enabled by default
+  toc: Option<TocConfig>,
+  /// build-time syntax highlighting of fenced code blocks
+  highlight: Option<HighlightConfig>,
+  /// render plain Markdown files to an HTML string instead of compiling them as MDX
+  markdown: Option<MarkdownConfig>,
+  /// import relative image sources so they go through the asset pipeline, enabled by default
+  images: Option<bool>,
+  /// check relative links to `.md`/`.mdx` files, `warn` by default
+  check_links: Option<CheckLinksConfig>,
+  /// preset for the JSX runtime, the provider and the exported component, `react` by default
+  framework: Option<Framework>,
+  /// `mdx-collection:<name>` modules indexing the pages of a directory
+  collections: Option<HashMap<String, CollectionOptions>>,
+  /// declarations of the collections, `mdx-collections.d.ts` by default
+  collections_dts: Option<DtsConfig>,
+  /// layout components by name, selected by the `layout` field of the frontmatter
+  layouts: Option<HashMap<String, String>>,
+  /// layout of the pages of a directory without a `layout` field
+  default_layouts: Option<HashMap<String, String>>,
+  /// built-in transforms enabled by name
+  transforms: Option<Vec<Transform>>,
+}
+
+/// JavaScript compiled from a Markdown or MDX file
+#[derive(Debug, Default)]
+pub struct CompiledModule {
+  pub code: String,
+  pub source_map: Option<String>,
+  /// reported to the log store, e.g. broken links
+  pub warnings: Vec<String>,
 }
 
-fn is_mdx_file(file_name: &String) -> bool {
+fn is_mdx_file(file_name: &str) -> bool {
   file_name.ends_with(".md") || file_name.ends_with(".mdx")
 }
 
+/// The value of `result`, its error is kept in `errors`
+fn config_value<T>(result: Result<T, String>, errors: &mut Vec<String>) -> Option<T> {
+  result.map_err(|e| errors.push(e)).ok()
+}
+
 impl FarmPluginMdx {
-  fn new(_: &Config, options: String) -> Self {
+  fn new(config: &Config, options: String) -> Self {
+    let mut config_errors = vec![];
     let plugin_options: FarmPluginMdxOptions =
       serde_json::from_str::<FarmPluginMdxOptions>(&options).unwrap();
+    let framework = plugin_options.framework.unwrap_or_default();
+    let transforms = Transforms::new(&plugin_options.transforms.unwrap_or_default());
+    let mut parse = plugin_options.parse.unwrap_or_default();
+    transforms.configure(&mut parse);
+    let collections = config_value(
+      plugin_options
+        .collections
+        .unwrap_or_default()
+        .iter()
+        .map(|(name, options)| {
+          Collection::new(name, options, &config.root).map(|collection| (name.clone(), collection))
+        })
+        .collect::<Result<HashMap<_, _>, _>>(),
+      &mut config_errors,
+    )
+    .unwrap_or_default();
+    let collections_dts = plugin_options
+      .collections_dts
+      .unwrap_or(DtsConfig::Enabled(true))
+      .path()
+      .filter(|_| !collections.is_empty())
+      .map(|path| Path::new(&config.root).join(path));
 
     Self {
       mdx_options: Options {
         development: plugin_options.development.unwrap_or(false),
-        provider_import_source: plugin_options.provider_import_source,
+        // only an explicit `framework` opts into a provider, it needs an extra package
+        provider_import_source: plugin_options.provider_import_source.or(
+          plugin_options
+            .framework
+            .map(|framework| framework.provider_import_source().into()),
+        ),
         jsx: plugin_options.jsx.unwrap_or(false),
         jsx_runtime: Some(plugin_options.jsx_runtime.unwrap_or(JsxRuntime::Automatic)),
         pragma: Some(
@@ -68,14 +181,149 @@ impl FarmPluginMdx {
             .pragma_import_source
             .unwrap_or("react".into()),
         ),
-        parse: plugin_options.parse.unwrap_or_default(),
-        jsx_import_source: Some(plugin_options.jsx_import_source.unwrap_or("react".into())),
+        parse,
+        jsx_import_source: Some(
+          plugin_options
+            .jsx_import_source
+            .unwrap_or(framework.jsx_import_source().into()),
+        ),
         filepath: plugin_options.filepath,
       },
       include: Some(plugin_options.include.unwrap_or("".into())),
       exclude: Some(plugin_options.exclude.unwrap_or("".into())),
+      frontmatter_variables: plugin_options.frontmatter_variables.unwrap_or(false),
+      toc: plugin_options
+        .toc
+        .unwrap_or(TocConfig::Enabled(true))
+        .options(),
+      highlighter: plugin_options
+        .highlight
+        .and_then(|highlight| highlight.options())
+        .and_then(|options| {
+          config_value(Highlighter::new(&options, &config.root), &mut config_errors)
+        }),
+      markdown: plugin_options
+        .markdown
+        .and_then(|markdown| markdown.options())
+        .and_then(|options| {
+          config_value(
+            MarkdownRenderer::new(&options, &config.root),
+            &mut config_errors,
+          )
+        }),
+      resolve_images: plugin_options.images.unwrap_or(true),
+      check_links: plugin_options
+        .check_links
+        .unwrap_or(CheckLinksConfig::Level(LinkLevel::Warn))
+        .level(),
+      framework,
+      collections,
+      collections_dts,
+      layouts: config_value(
+        Layouts::new(
+          &plugin_options.layouts.unwrap_or_default(),
+          &plugin_options.default_layouts.unwrap_or_default(),
+          &config.root,
+        ),
+        &mut config_errors,
+      )
+      .unwrap_or_default(),
+      transforms,
+      config_errors,
     }
   }
+
+  /// Compiles `source` to JavaScript, along with a source map back to `source`
+  /// when `source_map` is set.
+  fn compile_mdx(
+    &self,
+    resolved_path: &str,
+    source: &str,
+    source_map: bool,
+  ) -> farmfe_core::error::Result<CompiledModule> {
+    // the edits below keep every line in place, so positions in `content`
+    // are positions in `source` too
+    let frontmatter = parse_frontmatter(resolved_path, source)?;
+    // MDX itself would render the frontmatter as a thematic break and a paragraph
+    let mut content = match &frontmatter {
+      Some(frontmatter) => replace_frontmatter(source, frontmatter, self.frontmatter_variables),
+      None => source.to_string(),
+    };
+    if let Some(highlighter) = &self.highlighter {
+      // a document that does not parse is reported by `compile`
+      if let Ok(mdast) = to_mdast(&content, &self.mdx_options.parse) {
+        content = highlighter.highlight_code_blocks(&content, &mdast);
+      }
+    }
+    // the autolinks need heading ids even without `toc`
+    let id_options = self
+      .toc
+      .clone()
+      .or_else(|| self.transforms.heading_autolinks.then(TocOptions::default));
+    content = self.transforms.apply(
+      content,
+      &self.mdx_options.parse,
+      &id_options.clone().unwrap_or_default(),
+    );
+    if !self.layouts.is_empty() {
+      if let Ok(mdast) = to_mdast(&content, &self.mdx_options.parse) {
+        let data = frontmatter.as_ref().map(|frontmatter| &frontmatter.data);
+        if let Some(layout) = self.layouts.resolve(resolved_path, data, &mdast)? {
+          content = append_layout(
+            &content,
+            &mdast,
+            layout,
+            frontmatter.is_some(),
+            self.toc.is_some(),
+          );
+        }
+      }
+    }
+    let to_error = |message| message_to_compilation_error(resolved_path, source, &message);
+    let code = compile(&content, &self.mdx_options).map_err(to_error)?;
+
+    let mdast = to_mdast(&content, &self.mdx_options.parse).map_err(to_error)?;
+    let warnings = match self.check_links {
+      Some(level) => check_links(level, resolved_path, &mdast)?,
+      None => vec![],
+    };
+    let mut elements = collect_elements(&mdast);
+    let toc = id_options
+      .as_ref()
+      .map(|toc_options| collect_toc(&mdast, toc_options));
+    if let Some(toc) = &toc {
+      let headings = elements.iter_mut().filter(|element| element.is_heading());
+      for (element, id) in headings.zip(&toc.ids) {
+        element.id = Some(id.clone());
+      }
+    }
+    let mut rewriter = MdxRewriter::new(elements);
+    rewriter.resolve_images = self.resolve_images;
+    rewriter.external_links = self.transforms.external_links;
+    let (mut code, source_map) = rewrite_module(
+      resolved_path,
+      code,
+      &mut rewriter,
+      source_map.then_some(source),
+    )?;
+    code = self.framework.wrap_default_export(&code);
+    if let Some(toc) = toc.filter(|_| self.toc.is_some()) {
+      code.push_str(&stringify_toc(&toc.entries));
+    }
+    Ok(CompiledModule {
+      code,
+      source_map,
+      warnings,
+    })
+  }
+
+  /// Whether the file is rendered to HTML by the `markdown` option
+  fn is_markdown_file(&self, resolved_path: &str) -> bool {
+    self
+      .markdown
+      .as_ref()
+      .is_some_and(|markdown| markdown.matches(resolved_path))
+  }
 }
 
 impl Plugin for FarmPluginMdx {
@@ -83,14 +331,87 @@ impl Plugin for FarmPluginMdx {
     "FarmPluginMdx"
   }
 
+  /// Reports invalid options, writes the declarations of the collections and
+  /// the stylesheet of the highlight theme
+  fn build_start(
+    &self,
+    _context: &std::sync::Arc<farmfe_core::context::CompilationContext>,
+  ) -> farmfe_core::error::Result<Option<()>> {
+    if !self.config_errors.is_empty() {
+      return Err(CompilationError::GenericError(format!(
+        "[{}] {}",
+        self.name(),
+        self.config_errors.join("\n")
+      )));
+    }
+    if let Some(path) = &self.collections_dts {
+      write_dts(&self.collections, path).map_err(CompilationError::GenericError)?;
+    }
+    if let Some(highlighter) = &self.highlighter {
+      highlighter
+        .write_css()
+        .map_err(CompilationError::GenericError)?;
+    }
+    Ok(None)
+  }
+
+  fn resolve(
+    &self,
+    param: &farmfe_core::plugin::PluginResolveHookParam,
+    _context: &std::sync::Arc<farmfe_core::context::CompilationContext>,
+    _hook_context: &farmfe_core::plugin::PluginHookContext,
+  ) -> farmfe_core::error::Result<Option<PluginResolveHookResult>> {
+    let Some(name) = param.source.strip_prefix(COLLECTION_PREFIX) else {
+      return Ok(None);
+    };
+    if !self.collections.contains_key(name) {
+      return Err(CompilationError::GenericError(format!(
+        "[{}] Unknown collection `{name}`, add it to the `collections` option",
+        self.name()
+      )));
+    }
+    Ok(Some(PluginResolveHookResult {
+      resolved_path: param.source.clone(),
+      side_effects: false,
+      ..Default::default()
+    }))
+  }
+
   fn load(
     &self,
     param: &farmfe_core::plugin::PluginLoadHookParam,
-    _context: &std::sync::Arc<farmfe_core::context::CompilationContext>,
+    context: &std::sync::Arc<farmfe_core::context::CompilationContext>,
     _hook_context: &farmfe_core::plugin::PluginHookContext,
   ) -> farmfe_core::error::Result<Option<PluginLoadHookResult>> {
-    if is_mdx_file(&param.module_id) {
-      let content = read_to_string(param.resolved_path).unwrap();
+    if let Some(collection) = param
+      .resolved_path
+      .strip_prefix(COLLECTION_PREFIX)
+      .and_then(|name| self.collections.get(name))
+    {
+      let (content, files) = collection.render(&self.mdx_options.parse)?;
+      // the index changes with the frontmatter of its entries, and with the
+      // entries added to or removed from the directory
+      context.add_watch_files(
+        param.module_id.as_str().into(),
+        files
+          .iter()
+          .map(PathBuf::as_path)
+          .chain([collection.dir()])
+          .map(|path| ModuleId::new(&path.to_string_lossy(), "", &context.config.root))
+          .collect(),
+      )?;
+      return Ok(Some(PluginLoadHookResult {
+        content,
+        source_map: None,
+        module_type: ModuleType::Js,
+      }));
+    }
+    if is_mdx_file(&param.module_id) || self.is_markdown_file(param.resolved_path) {
+      let content =
+        read_to_string(param.resolved_path).map_err(|e| CompilationError::LoadError {
+          resolved_path: param.resolved_path.to_string(),
+          source: Some(Box::new(e)),
+        })?;
       return Ok(Some(farmfe_core::plugin::PluginLoadHookResult {
         content,
         source_map: None,
@@ -100,17 +421,61 @@ impl Plugin for FarmPluginMdx {
     Ok(None)
   }
 
+  /// Reloads the index of a collection when an entry is added, changed or
+  /// removed, and rewrites the declarations of the collections
+  fn update_modules(
+    &self,
+    params: &mut PluginUpdateModulesHookParam,
+    context: &std::sync::Arc<farmfe_core::context::CompilationContext>,
+  ) -> farmfe_core::error::Result<Option<()>> {
+    let mut indexes = self
+      .collections
+      .iter()
+      .filter(|(_, collection)| {
+        params
+          .paths
+          .iter()
+          .any(|(path, _)| collection.contains(Path::new(path)))
+      })
+      .map(|(name, _)| format!("{COLLECTION_PREFIX}{name}"))
+      .collect::<Vec<_>>();
+    if indexes.is_empty() {
+      return Ok(None);
+    }
+    indexes.sort();
+    {
+      // a new entry is not a module yet, only its index is updated
+      let module_graph = context.module_graph.read();
+      params.paths.retain(|(path, _)| {
+        !self
+          .collections
+          .values()
+          .any(|collection| collection.contains(Path::new(path)))
+          || module_graph.has_module(&ModuleId::new(path, "", &context.config.root))
+      });
+    }
+    for index in indexes {
+      if !params.paths.iter().any(|(path, _)| *path == index) {
+        params.paths.push((index, UpdateType::Updated));
+      }
+    }
+    if let Some(path) = &self.collections_dts {
+      write_dts(&self.collections, path).map_err(CompilationError::GenericError)?;
+    }
+    Ok(Some(()))
+  }
+
   fn transform(
     &self,
     param: &farmfe_core::plugin::PluginTransformHookParam,
-    _context: &std::sync::Arc<farmfe_core::context::CompilationContext>,
+    context: &std::sync::Arc<farmfe_core::context::CompilationContext>,
   ) -> farmfe_core::error::Result<Option<farmfe_core::plugin::PluginTransformHookResult>> {
     if param.module_type != ModuleType::Custom("mdx".to_string()) {
       return Ok(None);
     }
 
     if self.include != Some(String::from("")) {
-      let inc_reg = Regex::new(&format!("{}", self.include.as_ref().unwrap())).unwrap();
+      let inc_reg = Regex::new(self.include.as_ref().unwrap()).unwrap();
       if let Some(_text) = inc_reg.find(param.resolved_path) {
       } else {
         return Ok(None);
@@ -118,21 +483,437 @@ impl Plugin for FarmPluginMdx {
     }
 
     if self.exclude != Some(String::from("")) {
-      let exc_reg = Regex::new(&format!("{}", self.exclude.as_ref().unwrap())).unwrap();
+      let exc_reg = Regex::new(self.exclude.as_ref().unwrap()).unwrap();
       if let Some(_text) = exc_reg.find(param.resolved_path) {
         return Ok(None);
       }
     }
-    if param.module_id.ends_with(".mdx") || param.module_id.ends_with(".md") {
-      let code = compile(&param.content, &self.mdx_options);
-      let js_code = code.unwrap();
-      return Ok(Some(farmfe_core::plugin::PluginTransformHookResult {
-        content: js_code.to_string(),
-        module_type: Some(ModuleType::Jsx),
-        source_map: None,
-        ignore_previous_source_map: true,
-      }));
+    let (compiled, module_type) = if let Some(markdown) = self
+      .markdown
+      .as_ref()
+      .filter(|markdown| markdown.matches(param.resolved_path))
+    {
+      let compiled = markdown.render(
+        param.resolved_path,
+        &param.content,
+        self.toc.as_ref(),
+        self.check_links,
+      )?;
+      (compiled, ModuleType::Js)
+    } else if param.module_id.ends_with(".mdx") || param.module_id.ends_with(".md") {
+      let compiled = self.compile_mdx(
+        param.resolved_path,
+        &param.content,
+        context.config.sourcemap.enabled(false),
+      )?;
+      (compiled, ModuleType::Jsx)
+    } else {
+      return Ok(None);
+    };
+
+    for warning in compiled.warnings {
+      context
+        .log_store
+        .lock()
+        .add_warning(format!("[{}] {warning}", self.name()));
     }
-    return Ok(None);
+    Ok(Some(farmfe_core::plugin::PluginTransformHookResult {
+      content: compiled.code,
+      module_type: Some(module_type),
+      source_map: compiled.source_map,
+      ignore_previous_source_map: true,
+    }))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use farmfe_toolkit::sourcemap::SourceMap;
+
+  #[test]
+  fn test_frontmatter_variables() {
+    let plugin = FarmPluginMdx::new(
+      &Config::default(),
+      r#"{"frontmatterVariables": true}"#.to_string(),
+    );
+    let code = plugin
+      .compile_mdx("post.mdx", "---\ntitle: Hello\n---\n\n# {title}\n", false)
+      .unwrap()
+      .code;
+    assert!(code.contains(r#"export const frontmatter = {"#));
+    assert!(code.contains("export const { title } = frontmatter"));
+    assert!(code.contains("children: title"));
+  }
+
+  #[test]
+  fn test_toc() {
+    let plugin = FarmPluginMdx::new(
+      &Config::default(),
+      r#"{"toc": {"maxDepth": 2}}"#.to_string(),
+    );
+    let code = plugin
+      .compile_mdx(
+        "post.mdx",
+        "# Guide\n\n## Install *now*\n\n### Details\n",
+        false,
+      )
+      .unwrap()
+      .code;
+    assert!(code.contains("_jsxs(_components.h2, {\n                id: \"install-now\","));
+    assert!(code.contains("_jsx(_components.h3, {\n                id: \"details\","));
+    assert!(code.ends_with(
+      "export const toc = [{\"depth\":1,\"text\":\"Guide\",\"id\":\"guide\",\"children\":\
+       [{\"depth\":2,\"text\":\"Install now\",\"id\":\"install-now\",\"children\":[]}]}];\n"
+    ));
+
+    let plugin = FarmPluginMdx::new(&Config::default(), r#"{"toc": false}"#.to_string());
+    let code = plugin
+      .compile_mdx("post.mdx", "# Guide\n", false)
+      .unwrap()
+      .code;
+    assert!(!code.contains("toc") && !code.contains("id:"));
+  }
+
+  #[test]
+  fn test_compile_error() {
+    let plugin = FarmPluginMdx::new(&Config::default(), "{}".to_string());
+    let Err(CompilationError::ParseError { msg, .. }) = plugin.compile_mdx(
+      "post.mdx",
+      "---\ntitle: x\n---\n\n# Hi\n\nHello <Note title=>\n",
+      false,
+    ) else {
+      panic!("expected a parse error");
+    };
+    assert_eq!(
+      msg,
+      "Failed to parse post.mdx:7:19: Unexpected character `>` (U+003E) before attribute \
+       value, expected a character that can start an attribute value, such as `\"`, `'`, or \
+       `{` (markdown-rs:unexpected-character)\n\n\
+       5 | # Hi\n\
+       6 | \n\
+       7 | Hello <Note title=>\n  \
+       |                   ^"
+    );
+  }
+
+  /// The generated code a source position maps to, up to the end of its line
+  fn generated(code: &str, map: &str, line: u32, column: u32) -> String {
+    let map = SourceMap::from_slice(map.as_bytes()).unwrap();
+    let token = map
+      .tokens()
+      .find(|token| token.get_src() == (line, column))
+      .unwrap();
+    let line = code.lines().nth(token.get_dst_line() as usize).unwrap();
+    line[token.get_dst_col() as usize..].to_string()
+  }
+
+  #[test]
+  fn test_source_map() {
+    let plugin = FarmPluginMdx::new(&Config::default(), "{}".to_string());
+    let source = "---\ntitle: x\n---\n\n# Hello\n\nSome *text*\n";
+    let CompiledModule {
+      code, source_map, ..
+    } = plugin.compile_mdx("post.mdx", source, true).unwrap();
+    let map = source_map.unwrap();
+    let source_contents = SourceMap::from_slice(map.as_bytes())
+      .unwrap()
+      .get_source_contents(0)
+      .map(|contents| contents.to_string());
+    assert_eq!(source_contents.as_deref(), Some(source));
+    assert!(generated(&code, &map, 4, 0).starts_with("_jsx(_components.h1"));
+    assert!(generated(&code, &map, 6, 0).starts_with("_jsxs(_components.p"));
+    assert!(generated(&code, &map, 6, 5).starts_with("_jsx(_components.em"));
+
+    let plugin = FarmPluginMdx::new(&Config::default(), r#"{"jsx": true}"#.to_string());
+    let CompiledModule {
+      code, source_map, ..
+    } = plugin.compile_mdx("post.mdx", "# Hello\n", true).unwrap();
+    assert!(generated(&code, &source_map.unwrap(), 0, 0).starts_with("_components.h1 id="));
+  }
+
+  #[test]
+  fn test_images_and_links() {
+    let plugin = FarmPluginMdx::new(&Config::default(), "{}".to_string());
+    let resolved_path = format!("{}/post.mdx", env!("CARGO_MANIFEST_DIR"));
+    let compiled = plugin
+      .compile_mdx(
+        &resolved_path,
+        "![Diagram](./diagram.png)\n\nSee [usage](./README.md#usage) and [api](./api.mdx).\n",
+        false,
+      )
+      .unwrap();
+    assert!(compiled
+      .code
+      .contains("import _mdxImage0 from \"./diagram.png\";\n"));
+    assert!(compiled.code.contains("src: _mdxImage0,"));
+    assert_eq!(
+      compiled.warnings,
+      vec![format!(
+        "{resolved_path}:3:36: `./api.mdx` links to a file that does not exist"
+      )]
+    );
+
+    let plugin = FarmPluginMdx::new(
+      &Config::default(),
+      r#"{"images": false, "checkLinks": "error"}"#.to_string(),
+    );
+    let content = "![Diagram](./diagram.png) [api](./api.mdx)\n";
+    assert!(plugin.compile_mdx(&resolved_path, content, false).is_err());
+    let plugin = FarmPluginMdx::new(
+      &Config::default(),
+      r#"{"images": false, "checkLinks": false}"#.to_string(),
+    );
+    let compiled = plugin.compile_mdx(&resolved_path, content, false).unwrap();
+    assert!(compiled.code.contains("src: \"./diagram.png\""));
+    assert!(compiled.warnings.is_empty());
+  }
+
+  #[test]
+  fn test_framework() {
+    let compile = |options: &str| {
+      FarmPluginMdx::new(&Config::default(), options.to_string())
+        .compile_mdx("post.mdx", "# Hello\n", false)
+        .unwrap()
+        .code
+    };
+    let code = compile("{}");
+    assert!(code.contains("from \"react/jsx-runtime\""));
+    assert!(!code.contains("useMDXComponents"));
+
+    let code = compile(r#"{"framework": "preact"}"#);
+    assert!(code.contains("from \"preact/jsx-runtime\""));
+    assert!(
+      code.contains("import { useMDXComponents as _provideComponents } from \"@mdx-js/preact\"")
+    );
+
+    let code = compile(r#"{"framework": "vue", "providerImportSource": "./mdx-components"}"#);
+    assert!(code.contains("from \"vue/jsx-runtime\""));
+    assert!(code.contains("from \"./mdx-components\""));
+    assert!(code.contains("export default _defineComponent({"));
+
+    let code = compile(r#"{"framework": "solid"}"#);
+    assert!(code.contains("from \"solid-js/h/jsx-runtime\""));
+    assert!(code.contains("from \"solid-mdx\""));
+    assert!(code.contains("export default MDXContent;"));
+  }
+
+  #[test]
+  fn test_layouts() {
+    let config = Config {
+      root: "/site".to_string(),
+      ..Default::default()
+    };
+    let plugin = FarmPluginMdx::new(
+      &config,
+      r#"{"layouts": {"post": "@/layouts/Post"}, "defaultLayouts": {"posts": "post"}}"#.to_string(),
+    );
+    let source = "---\nlayout: post\n---\n\n# Hello\n";
+    let CompiledModule {
+      code, source_map, ..
+    } = plugin.compile_mdx("post.mdx", source, true).unwrap();
+    assert!(code.contains("import _MDXLayout from \"@/layouts/Post\";"));
+    assert!(code.contains(
+      "return _jsx(_MDXLayout, Object.assign({}, props, {\n        \
+       frontmatter: frontmatter,\n        toc: toc\n    }));"
+    ));
+    assert!(code.contains("return _jsx(MDXLayout, Object.assign({}, props, {"));
+    // the layout does not move the content
+    assert!(generated(&code, &source_map.unwrap(), 4, 0).starts_with("_jsx(_components.h1"));
+
+    let code = plugin
+      .compile_mdx("/site/posts/a.mdx", "# Hello\n", false)
+      .unwrap()
+      .code;
+    assert!(code.contains("frontmatter: {},"));
+    let code = plugin
+      .compile_mdx(
+        "/site/posts/a.mdx",
+        "---\nlayout: false\n---\n\n# Hello\n",
+        false,
+      )
+      .unwrap()
+      .code;
+    assert!(!code.contains("_MDXLayout"));
+  }
+
+  #[test]
+  fn test_frontmatter_variables_keep_generated_exports() {
+    let plugin = FarmPluginMdx::new(
+      &Config::default(),
+      r#"{"frontmatterVariables": true}"#.to_string(),
+    );
+    let code = plugin
+      .compile_mdx(
+        "post.mdx",
+        "---\ntoc: false\ntitle: Hi\n---\n\n# {title}\n",
+        false,
+      )
+      .unwrap()
+      .code;
+    assert!(code.contains("export const { title } = frontmatter;"));
+    assert_eq!(code.matches("export const toc").count(), 1);
+  }
+
+  #[test]
+  fn test_markdown_files_skip_mdx_steps() {
+    let plugin = FarmPluginMdx::new(
+      &Config::default(),
+      r#"{"markdown": true, "highlight": true, "transforms": ["emoji"]}"#.to_string(),
+    );
+    let context = std::sync::Arc::new(
+      farmfe_core::context::CompilationContext::new(Config::default(), vec![]).unwrap(),
+    );
+    let result = plugin
+      .transform(
+        &farmfe_core::plugin::PluginTransformHookParam {
+          module_id: "notes.md".to_string(),
+          content: "Hi :wave:\n\n```js\nlet a = 1;\n```\n".to_string(),
+          module_type: ModuleType::Custom("mdx".to_string()),
+          resolved_path: "notes.md",
+          query: vec![],
+          meta: Default::default(),
+          source_map_chain: vec![],
+        },
+        &context,
+      )
+      .unwrap()
+      .unwrap();
+    assert!(result.content.contains(
+      "<p>Hi :wave:</p>\\n<pre><code class=\\\"language-js\\\">let a = 1;\\n</code></pre>"
+    ));
+  }
+
+  #[test]
+  fn test_unknown_default_layout() {
+    let plugin = FarmPluginMdx::new(
+      &Config::default(),
+      r#"{"defaultLayouts": {"posts": "post"}}"#.to_string(),
+    );
+    let context = std::sync::Arc::new(
+      farmfe_core::context::CompilationContext::new(Config::default(), vec![]).unwrap(),
+    );
+    let err = plugin.build_start(&context).unwrap_err();
+    assert!(matches!(
+      err,
+      CompilationError::GenericError(msg) if msg.contains("the unknown layout `post`")
+    ));
+  }
+
+  #[test]
+  fn test_transforms() {
+    let plugin = FarmPluginMdx::new(
+      &Config::default(),
+      r#"{"toc": false, "transforms": ["gfm", "headingAutolinks", "externalLinks", "emoji", "math", "admonitions"]}"#
+        .to_string(),
+    );
+    let source = ":::note\n# Hi :wave:\n\n| $x^2$ | ~~no~~ |\n| - | - |\n\n\
+                  [Farm](https://farmfe.org)\n:::\n";
+    let CompiledModule {
+      code, source_map, ..
+    } = plugin.compile_mdx("post.mdx", source, true).unwrap();
+    assert!(code.contains("className: \"admonition admonition-note\""));
+    assert!(code.contains("id: \"hi-\""));
+    assert!(code.contains("href: \"#hi-\",\n"));
+    assert!(code.contains("className: \"heading-anchor\""));
+    assert!(code.contains("\"Hi 👋 \""));
+    assert!(code.contains("_jsx(_components.del"));
+    assert!(code.contains("_jsx(\"mi\", {\n"));
+    assert!(code.contains("target: \"_blank\""));
+    assert!(!code.contains("export const toc"));
+    // the edits keep the lines in place
+    let map = source_map.unwrap();
+    assert!(generated(&code, &map, 1, 0).starts_with("_jsxs(_components.h1"));
+    assert!(generated(&code, &map, 3, 0).starts_with("_jsxs(_components.table"));
+
+    let plugin = FarmPluginMdx::new(&Config::default(), "{}".to_string());
+    let code = plugin
+      .compile_mdx("post.mdx", "Hi :wave: $x$ ~~no~~\n", false)
+      .unwrap()
+      .code;
+    assert!(code.contains("\"Hi :wave: $x$ ~~no~~\""));
+  }
+
+  #[test]
+  fn test_invalid_collection() {
+    let plugin = FarmPluginMdx::new(
+      &Config::default(),
+      r#"{"collections": {"blog": {"dir": "blog", "pattern": "[a"}}}"#.to_string(),
+    );
+    let context = std::sync::Arc::new(
+      farmfe_core::context::CompilationContext::new(Config::default(), vec![]).unwrap(),
+    );
+    let err = plugin.build_start(&context).unwrap_err();
+    assert!(
+      matches!(err, CompilationError::GenericError(msg) if msg.starts_with("[FarmPluginMdx] Invalid"))
+    );
+  }
+
+  #[test]
+  fn test_invalid_highlight_theme() {
+    let plugin = FarmPluginMdx::new(
+      &Config::default(),
+      r#"{"highlight": {"theme": "missing"}, "markdown": {"files": ["[a"]}}"#.to_string(),
+    );
+    assert!(plugin.highlighter.is_none());
+    let context = std::sync::Arc::new(
+      farmfe_core::context::CompilationContext::new(Config::default(), vec![]).unwrap(),
+    );
+    let err = plugin.build_start(&context).unwrap_err();
+    assert!(matches!(
+      err,
+      CompilationError::GenericError(msg)
+        if msg.contains("Unknown highlight theme `missing`")
+          && msg.contains("Invalid `markdown.files` glob `[a`")
+    ));
+  }
+
+  #[test]
+  fn test_collection_updates() {
+    let root = std::env::temp_dir().join(format!("farm-mdx-updates-{}", std::process::id()));
+    std::fs::create_dir_all(root.join("blog")).unwrap();
+    std::fs::write(root.join("blog/a.mdx"), "# A\n").unwrap();
+    let config = Config {
+      root: root.to_string_lossy().to_string(),
+      ..Default::default()
+    };
+    let plugin = FarmPluginMdx::new(
+      &config,
+      r#"{"collections": {"blog": {"dir": "blog"}}}"#.to_string(),
+    );
+    let dts = root.join("mdx-collections.d.ts");
+    assert!(!dts.exists());
+
+    let context =
+      std::sync::Arc::new(farmfe_core::context::CompilationContext::new(config, vec![]).unwrap());
+    plugin.build_start(&context).unwrap();
+    std::fs::remove_file(&dts).unwrap();
+    let added = root.join("blog/b.mdx").to_string_lossy().to_string();
+    let mut params = PluginUpdateModulesHookParam {
+      paths: vec![
+        (added, UpdateType::Added),
+        ("src/main.ts".to_string(), UpdateType::Updated),
+      ],
+    };
+    plugin.update_modules(&mut params, &context).unwrap();
+    let paths = params
+      .paths
+      .iter()
+      .map(|(path, update_type)| (path.as_str(), matches!(update_type, UpdateType::Updated)))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      paths,
+      vec![("src/main.ts", true), ("mdx-collection:blog", true)]
+    );
+    assert!(dts.exists());
+
+    let mut params = PluginUpdateModulesHookParam {
+      paths: vec![("src/main.ts".to_string(), UpdateType::Updated)],
+    };
+    assert!(plugin
+      .update_modules(&mut params, &context)
+      .unwrap()
+      .is_none());
+    std::fs::remove_dir_all(root).unwrap();
   }
 }
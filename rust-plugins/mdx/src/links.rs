@@ -0,0 +1,144 @@
+use std::path::Path;
+
+use farmfe_core::error::CompilationError;
+use lazy_static::lazy_static;
+use markdown::mdast::Node;
+use regex::Regex;
+use serde::Deserialize;
+
+lazy_static! {
+  static ref URL_SCHEME: Regex = Regex::new(r"^[a-zA-Z][a-zA-Z\d+\-.]*:").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum LinkLevel {
+  Warn,
+  Error,
+}
+
+/// `checkLinks` option, `true` is the same as `warn`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum CheckLinksConfig {
+  Enabled(bool),
+  Level(LinkLevel),
+}
+
+impl CheckLinksConfig {
+  pub fn level(&self) -> Option<LinkLevel> {
+    match self {
+      CheckLinksConfig::Enabled(true) => Some(LinkLevel::Warn),
+      CheckLinksConfig::Enabled(false) => None,
+      CheckLinksConfig::Level(level) => Some(*level),
+    }
+  }
+}
+
+/// A url relative to the document, e.g. `./a.png` or `guide/intro.md`, not
+/// `/a.png`, `#install` or `https://farmfe.org`.
+pub fn is_relative_url(url: &str) -> bool {
+  !url.is_empty() && !url.starts_with(['/', '#', '?', '\\']) && !URL_SCHEME.is_match(url)
+}
+
+/// A link to another site, e.g. `https://farmfe.org` or `//farmfe.org`
+pub fn is_external_url(url: &str) -> bool {
+  url.starts_with("http://") || url.starts_with("https://") || url.starts_with("//")
+}
+
+/// Checks that the relative links of `mdast` to `.md`/`.mdx` files point to
+/// existing files. Returns the warnings, or an error for [`LinkLevel::Error`].
+pub fn check_links(
+  level: LinkLevel,
+  resolved_path: &str,
+  mdast: &Node,
+) -> Result<Vec<String>, CompilationError> {
+  let dir = Path::new(resolved_path).parent().unwrap_or(Path::new(""));
+  let mut links = vec![];
+  collect_links(mdast, &mut links);
+  let mut messages = vec![];
+  for (url, node) in links {
+    if !is_relative_url(url) {
+      continue;
+    }
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    if !(path.ends_with(".md") || path.ends_with(".mdx")) || dir.join(path).exists() {
+      continue;
+    }
+    let place = node
+      .position()
+      .map(|position| format!(":{}:{}", position.start.line, position.start.column))
+      .unwrap_or_default();
+    messages.push(format!(
+      "{resolved_path}{place}: `{url}` links to a file that does not exist"
+    ));
+  }
+  match level {
+    LinkLevel::Error if !messages.is_empty() => Err(CompilationError::TransformError {
+      resolved_path: resolved_path.to_string(),
+      msg: messages.join("\n"),
+    }),
+    _ => Ok(messages),
+  }
+}
+
+/// Links and definitions, footnote definitions included
+fn collect_links<'a>(node: &'a Node, links: &mut Vec<(&'a str, &'a Node)>) {
+  match node {
+    Node::Link(link) => links.push((&link.url, node)),
+    Node::Definition(definition) => links.push((&definition.url, node)),
+    _ => {}
+  }
+  for child in node.children().into_iter().flatten() {
+    collect_links(child, links);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mdast::to_mdast;
+
+  #[test]
+  fn test_is_relative_url() {
+    assert!(is_relative_url("./a.png"));
+    assert!(is_relative_url("../guide/intro.md#install"));
+    assert!(is_relative_url("a.png"));
+    assert!(!is_relative_url("/a.png"));
+    assert!(!is_relative_url("#install"));
+    assert!(!is_relative_url("https://farmfe.org"));
+    assert!(!is_relative_url("mailto:a@b.c"));
+    assert!(!is_relative_url("data:image/png;base64,AA=="));
+  }
+
+  #[test]
+  fn test_is_external_url() {
+    assert!(is_external_url("https://farmfe.org"));
+    assert!(is_external_url("//farmfe.org/docs"));
+    assert!(!is_external_url("/docs"));
+    assert!(!is_external_url("./a.md"));
+    assert!(!is_external_url("mailto:a@b.c"));
+  }
+
+  #[test]
+  fn test_check_links() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let resolved_path = format!("{dir}/page.mdx");
+    let mdast = to_mdast(
+      "[ok](./README.md#usage) [missing](./missing.md)\n\n\
+       [web](https://farmfe.org/a.md) [image](./a.png)[^1]\n\n[ref]: ../missing.mdx\n\n\
+       [^1]: [note](./note.md)\n",
+      &mdxjs::MdxParseOptions::gfm(),
+    )
+    .unwrap();
+    assert_eq!(
+      check_links(LinkLevel::Warn, &resolved_path, &mdast).unwrap(),
+      vec![
+        format!("{resolved_path}:1:25: `./missing.md` links to a file that does not exist"),
+        format!("{resolved_path}:5:1: `../missing.mdx` links to a file that does not exist"),
+        format!("{resolved_path}:7:7: `./note.md` links to a file that does not exist"),
+      ]
+    );
+    assert!(check_links(LinkLevel::Error, &resolved_path, &mdast).is_err());
+  }
+}
@@ -0,0 +1,245 @@
+use std::path::Path;
+
+use farmfe_core::{error::CompilationError, serde_json};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use markdown::{mdast::Node, CompileOptions, Options, ParseOptions};
+use serde::Deserialize;
+
+use crate::{
+  error::message_to_compilation_error,
+  frontmatter::parse_frontmatter,
+  links::{check_links, LinkLevel},
+  mdast::walk,
+  toc::{collect_toc, stringify_toc, TocOptions},
+  CompiledModule,
+};
+
+/// `markdown` option, `true` renders `.md` files to HTML
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum MarkdownConfig {
+  Enabled(bool),
+  Options(MarkdownOptions),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MarkdownOptions {
+  /// Files with these extensions are rendered to HTML
+  pub extensions: Vec<String>,
+  /// Globs relative to the root, matching files are rendered to HTML too
+  pub files: Vec<String>,
+  /// Keep raw HTML instead of escaping it
+  pub allow_dangerous_html: bool,
+}
+
+impl Default for MarkdownOptions {
+  fn default() -> Self {
+    Self {
+      extensions: vec![".md".to_string()],
+      files: vec![],
+      allow_dangerous_html: false,
+    }
+  }
+}
+
+impl MarkdownConfig {
+  pub fn options(&self) -> Option<MarkdownOptions> {
+    match self {
+      MarkdownConfig::Enabled(true) => Some(MarkdownOptions::default()),
+      MarkdownConfig::Enabled(false) => None,
+      MarkdownConfig::Options(options) => Some(options.clone()),
+    }
+  }
+}
+
+/// Renders GFM Markdown to an HTML string, for files that are not meant to be
+/// compiled as MDX.
+#[derive(Debug)]
+pub struct MarkdownRenderer {
+  extensions: Vec<String>,
+  files: GlobSet,
+  root: String,
+  allow_dangerous_html: bool,
+}
+
+impl MarkdownRenderer {
+  pub fn new(options: &MarkdownOptions, root: &str) -> Result<Self, String> {
+    let mut files = GlobSetBuilder::new();
+    for pattern in &options.files {
+      files.add(
+        Glob::new(pattern)
+          .map_err(|e| format!("Invalid `markdown.files` glob `{pattern}`: {e}"))?,
+      );
+    }
+    Ok(Self {
+      extensions: options.extensions.clone(),
+      files: files.build().map_err(|e| e.to_string())?,
+      root: root.to_string(),
+      allow_dangerous_html: options.allow_dangerous_html,
+    })
+  }
+
+  pub fn matches(&self, resolved_path: &str) -> bool {
+    if self
+      .extensions
+      .iter()
+      .any(|extension| resolved_path.ends_with(extension.as_str()))
+    {
+      return true;
+    }
+    let relative_path = Path::new(resolved_path)
+      .strip_prefix(&self.root)
+      .unwrap_or(Path::new(resolved_path))
+      .to_string_lossy()
+      .replace('\\', "/");
+    self.files.is_match(relative_path)
+  }
+
+  /// Compiles `content` to a module exporting `html` (also the default export),
+  /// `frontmatter` and, unless `toc` is `None`, `toc`. Relative images are left
+  /// as they are, the HTML is not processed by the bundler.
+  pub fn render(
+    &self,
+    resolved_path: &str,
+    content: &str,
+    toc: Option<&TocOptions>,
+    links: Option<LinkLevel>,
+  ) -> Result<CompiledModule, CompilationError> {
+    // the frontmatter is left as empty lines, so positions stay those of the file
+    let (frontmatter, body) = match parse_frontmatter(resolved_path, content)? {
+      Some(frontmatter) => (
+        frontmatter.data,
+        format!(
+          "{}{}",
+          "\n".repeat(frontmatter.lines),
+          &content[frontmatter.len..]
+        ),
+      ),
+      None => (Default::default(), content.to_string()),
+    };
+    let to_error = |message| message_to_compilation_error(resolved_path, content, &message);
+    let options = Options {
+      parse: ParseOptions::gfm(),
+      compile: CompileOptions {
+        allow_dangerous_html: self.allow_dangerous_html,
+        ..CompileOptions::gfm()
+      },
+    };
+    let mut html = markdown::to_html_with_options(&body, &options).map_err(to_error)?;
+
+    let mut code = format!(
+      "export const frontmatter = {};\n",
+      serde_json::to_string(&frontmatter).unwrap()
+    );
+    let mdast = markdown::to_mdast(&body, &options.parse).map_err(to_error)?;
+    let warnings = match links {
+      Some(level) => check_links(level, resolved_path, &mdast)?,
+      None => vec![],
+    };
+    if let Some(toc_options) = toc {
+      let toc = collect_toc(&mdast, toc_options);
+      let mut depths = vec![];
+      walk(&mdast, &mut |node| {
+        if let Node::Heading(heading) = node {
+          depths.push(heading.depth);
+        }
+      });
+      html = add_heading_ids(&html, depths.into_iter().zip(&toc.ids));
+      code.push_str(&stringify_toc(&toc.entries));
+    }
+    code.push_str(&format!(
+      "export const html = {};\nexport default html;\n",
+      serde_json::to_string(&html).unwrap()
+    ));
+    Ok(CompiledModule {
+      code,
+      source_map: None,
+      warnings,
+    })
+  }
+}
+
+/// Sets `id` on the rendered headings, in document order. markdown-rs writes
+/// `<h2>` without attributes, so raw HTML headings, which usually have some,
+/// are left alone.
+fn add_heading_ids<'a>(html: &str, headings: impl Iterator<Item = (u8, &'a String)>) -> String {
+  let mut result = String::with_capacity(html.len());
+  let mut rest = html;
+  for (depth, id) in headings {
+    let tag = format!("<h{depth}>");
+    let Some(index) = rest.find(&tag) else {
+      break;
+    };
+    result.push_str(&rest[..index]);
+    // slugs only keep letters, digits, `-` and `_`
+    result.push_str(&format!("<h{depth} id=\"{id}\">"));
+    rest = &rest[index + tag.len()..];
+  }
+  result.push_str(rest);
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_matches() {
+    let renderer = MarkdownRenderer::new(
+      &MarkdownOptions {
+        extensions: vec![".markdown".to_string()],
+        files: vec!["docs/**/*.md".to_string()],
+        ..Default::default()
+      },
+      "/root",
+    )
+    .unwrap();
+    assert!(renderer.matches("/root/README.markdown"));
+    assert!(renderer.matches("/root/docs/guide/intro.md"));
+    assert!(!renderer.matches("/root/src/intro.md"));
+    assert!(!renderer.matches("/root/docs/page.mdx"));
+  }
+
+  #[test]
+  fn test_render() {
+    let renderer = MarkdownRenderer::new(&MarkdownOptions::default(), "/root").unwrap();
+    let code = renderer
+      .render(
+        "/root/a.md",
+        "---\ntitle: Hi\n---\n\n# Hello {name}\n\n| a < b |\n| - |\n| ~~x~~ |\n\n<div>raw</div>\n",
+        Some(&TocOptions::default()),
+        None,
+      )
+      .unwrap()
+      .code;
+    assert_eq!(
+      code,
+      "export const frontmatter = {\"title\":\"Hi\"};\n\
+       export const toc = [{\"depth\":1,\"text\":\"Hello {name}\",\"id\":\"hello-name\",\"children\":[]}];\n\
+       export const html = \"<h1 id=\\\"hello-name\\\">Hello {name}</h1>\\n\
+       <table>\\n<thead>\\n<tr>\\n<th>a &lt; b</th>\\n</tr>\\n</thead>\\n\
+       <tbody>\\n<tr>\\n<td><del>x</del></td>\\n</tr>\\n</tbody>\\n</table>\\n\
+       &lt;div&gt;raw&lt;/div&gt;\\n\";\n\
+       export default html;\n"
+    );
+  }
+
+  #[test]
+  fn test_render_positions() {
+    let renderer = MarkdownRenderer::new(&MarkdownOptions::default(), "/root").unwrap();
+    let warnings = renderer
+      .render(
+        "/root/a.md",
+        "---\ntitle: Hi\n---\n\n[missing](./missing.md)\n",
+        None,
+        Some(LinkLevel::Warn),
+      )
+      .unwrap()
+      .warnings;
+    assert_eq!(
+      warnings,
+      vec!["/root/a.md:5:1: `./missing.md` links to a file that does not exist".to_string()]
+    );
+  }
+}
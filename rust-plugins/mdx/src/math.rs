@@ -0,0 +1,435 @@
+use std::{iter::Peekable, str::Chars};
+
+use farmfe_core::serde_json;
+
+/// A node of the MathML tree
+#[derive(Debug, PartialEq)]
+enum MathNode {
+  Identifier(String),
+  Number(String),
+  Operator(String),
+  Text(String),
+  Row(Vec<MathNode>),
+  Fraction(Box<MathNode>, Box<MathNode>),
+  Sqrt(Box<MathNode>),
+  Root(Box<MathNode>, Box<MathNode>),
+  Scripts {
+    base: Box<MathNode>,
+    sub: Option<Box<MathNode>>,
+    sup: Option<Box<MathNode>>,
+  },
+  Space(&'static str),
+  /// An unsupported command, shown as it is written
+  Error(String),
+}
+
+/// The opening and closing JSX of `tex` rendered to MathML, with the class
+/// names KaTeX uses. The TeX source goes in between, as the annotation, so
+/// callers can spread it over the lines it was written on.
+pub fn math_to_jsx(tex: &str, display: bool) -> (String, String) {
+  let mut parser = TexParser {
+    chars: tex.chars().peekable(),
+  };
+  let row = parser.parse_row(false);
+  let mathml = MathNode::Row(row).to_jsx();
+  let (display, wrapper) = if display {
+    ("block", "<span className=\"katex-display\">")
+  } else {
+    ("inline", "")
+  };
+  let open = format!(
+    "{wrapper}<span className=\"katex\"><math display=\"{display}\"><semantics>{mathml}\
+     <annotation encoding=\"application/x-tex\">"
+  );
+  let close = format!(
+    "</annotation></semantics></math></span>{}",
+    if wrapper.is_empty() { "" } else { "</span>" }
+  );
+  (open, close)
+}
+
+struct TexParser<'a> {
+  chars: Peekable<Chars<'a>>,
+}
+
+impl TexParser<'_> {
+  /// Nodes up to the end of the input or, in a `group`, the closing `}`
+  fn parse_row(&mut self, group: bool) -> Vec<MathNode> {
+    let mut row = vec![];
+    while let Some(c) = self.chars.peek().copied() {
+      match c {
+        '}' if group => {
+          self.chars.next();
+          break;
+        }
+        '^' | '_' => {
+          self.chars.next();
+          let script = self.parse_argument();
+          let base = row.pop().unwrap_or(MathNode::Row(vec![]));
+          row.push(attach_script(base, c == '^', script));
+        }
+        _ => {
+          if let Some(node) = self.parse_atom() {
+            row.push(node);
+          }
+        }
+      }
+    }
+    row
+  }
+
+  /// A single node, `None` for whitespace and unmatched braces
+  fn parse_atom(&mut self) -> Option<MathNode> {
+    let c = self.chars.next()?;
+    Some(match c {
+      c if c.is_whitespace() => return None,
+      '{' => MathNode::Row(self.parse_row(true)),
+      '}' => return None,
+      '\\' => return self.parse_command(),
+      c if c.is_ascii_digit()
+        || (c == '.' && self.chars.peek().is_some_and(char::is_ascii_digit)) =>
+      {
+        let mut number = c.to_string();
+        while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+          number.push(c);
+        }
+        MathNode::Number(number)
+      }
+      c if c.is_alphabetic() => MathNode::Identifier(c.to_string()),
+      '\'' => MathNode::Operator("′".to_string()),
+      c => MathNode::Operator(c.to_string()),
+    })
+  }
+
+  /// The argument of a command or a script: a group or a single atom
+  fn parse_argument(&mut self) -> MathNode {
+    while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    match self.parse_atom() {
+      Some(MathNode::Row(mut row)) if row.len() == 1 => row.pop().unwrap(),
+      Some(node) => node,
+      None => MathNode::Row(vec![]),
+    }
+  }
+
+  /// The raw text of a `{...}` argument, for `\text`
+  fn parse_text_argument(&mut self) -> String {
+    while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    if self.chars.next_if_eq(&'{').is_none() {
+      return self.chars.next().map(String::from).unwrap_or_default();
+    }
+    let mut text = String::new();
+    let mut depth = 0;
+    for c in self.chars.by_ref() {
+      match c {
+        '{' => depth += 1,
+        '}' if depth == 0 => break,
+        '}' => depth -= 1,
+        _ => {}
+      }
+      text.push(c);
+    }
+    text
+  }
+
+  fn parse_command(&mut self) -> Option<MathNode> {
+    let mut name = String::new();
+    while let Some(c) = self.chars.next_if(|c| c.is_ascii_alphabetic()) {
+      name.push(c);
+    }
+    if name.is_empty() {
+      // `\,`, `\{`, `\\`...
+      name = self.chars.next()?.to_string();
+    }
+    Some(match name.as_str() {
+      "frac" | "dfrac" | "tfrac" => {
+        let numerator = self.parse_argument();
+        let denominator = self.parse_argument();
+        MathNode::Fraction(Box::new(numerator), Box::new(denominator))
+      }
+      "sqrt" => {
+        let index = if self.chars.next_if_eq(&'[').is_some() {
+          let mut index = String::new();
+          while let Some(c) = self.chars.next_if(|c| *c != ']') {
+            index.push(c);
+          }
+          self.chars.next();
+          Some(
+            TexParser {
+              chars: index.chars().peekable(),
+            }
+            .parse_row(false),
+          )
+        } else {
+          None
+        };
+        let radicand = Box::new(self.parse_argument());
+        match index {
+          Some(index) => MathNode::Root(radicand, Box::new(MathNode::Row(index))),
+          None => MathNode::Sqrt(radicand),
+        }
+      }
+      "text" | "textrm" | "mbox" => MathNode::Text(self.parse_text_argument()),
+      "mathrm" | "operatorname" => MathNode::Identifier(self.parse_text_argument()),
+      // the font is not rendered, the content is
+      "mathbf" | "mathit" | "mathbb" | "mathcal" | "mathsf" | "boldsymbol" => self.parse_argument(),
+      // delimiters stretch on their own in MathML
+      "left" | "right" => {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match self.parse_atom()? {
+          MathNode::Operator(delimiter) if delimiter == "." => return None,
+          node => node,
+        }
+      }
+      "," => MathNode::Space("0.1667em"),
+      ":" | ">" => MathNode::Space("0.2222em"),
+      ";" => MathNode::Space("0.2778em"),
+      " " => MathNode::Space("0.25em"),
+      "quad" => MathNode::Space("1em"),
+      "qquad" => MathNode::Space("2em"),
+      "!" => MathNode::Space("-0.1667em"),
+      "{" | "}" | "%" | "$" | "#" | "&" | "_" => MathNode::Operator(name),
+      name if FUNCTIONS.contains(&name) => MathNode::Identifier(name.to_string()),
+      name => match symbol(name) {
+        Some((symbol, true)) => MathNode::Identifier(symbol.to_string()),
+        Some((symbol, false)) => MathNode::Operator(symbol.to_string()),
+        None => MathNode::Error(format!("\\{name}")),
+      },
+    })
+  }
+}
+
+fn attach_script(base: MathNode, sup: bool, script: MathNode) -> MathNode {
+  let script = Some(Box::new(script));
+  match base {
+    // `x_1^2`
+    MathNode::Scripts {
+      base,
+      sub: Some(sub),
+      sup: None,
+    } if sup => MathNode::Scripts {
+      base,
+      sub: Some(sub),
+      sup: script,
+    },
+    MathNode::Scripts {
+      base,
+      sub: None,
+      sup: Some(sup_script),
+    } if !sup => MathNode::Scripts {
+      base,
+      sub: script,
+      sup: Some(sup_script),
+    },
+    base => {
+      let (sub, sup) = if sup { (None, script) } else { (script, None) };
+      MathNode::Scripts {
+        base: Box::new(base),
+        sub,
+        sup,
+      }
+    }
+  }
+}
+
+const FUNCTIONS: [&str; 19] = [
+  "sin", "cos", "tan", "cot", "sec", "csc", "log", "ln", "exp", "lim", "max", "min", "sup", "inf",
+  "det", "gcd", "arg", "deg", "dim",
+];
+
+/// The character of a symbol command, and whether it is an identifier rather
+/// than an operator
+fn symbol(name: &str) -> Option<(&'static str, bool)> {
+  let identifier = match name {
+    "alpha" => "α",
+    "beta" => "β",
+    "gamma" => "γ",
+    "delta" => "δ",
+    "epsilon" => "ϵ",
+    "varepsilon" => "ε",
+    "zeta" => "ζ",
+    "eta" => "η",
+    "theta" => "θ",
+    "vartheta" => "ϑ",
+    "iota" => "ι",
+    "kappa" => "κ",
+    "lambda" => "λ",
+    "mu" => "μ",
+    "nu" => "ν",
+    "xi" => "ξ",
+    "pi" => "π",
+    "rho" => "ρ",
+    "sigma" => "σ",
+    "tau" => "τ",
+    "upsilon" => "υ",
+    "phi" => "ϕ",
+    "varphi" => "φ",
+    "chi" => "χ",
+    "psi" => "ψ",
+    "omega" => "ω",
+    "Gamma" => "Γ",
+    "Delta" => "Δ",
+    "Theta" => "Θ",
+    "Lambda" => "Λ",
+    "Xi" => "Ξ",
+    "Pi" => "Π",
+    "Sigma" => "Σ",
+    "Phi" => "Φ",
+    "Psi" => "Ψ",
+    "Omega" => "Ω",
+    "infty" => "∞",
+    "partial" => "∂",
+    "nabla" => "∇",
+    "hbar" => "ℏ",
+    "ell" => "ℓ",
+    "emptyset" => "∅",
+    _ => "",
+  };
+  if !identifier.is_empty() {
+    return Some((identifier, true));
+  }
+  let operator = match name {
+    "times" => "×",
+    "cdot" => "⋅",
+    "div" => "÷",
+    "pm" => "±",
+    "mp" => "∓",
+    "ast" => "∗",
+    "circ" => "∘",
+    "leq" | "le" => "≤",
+    "geq" | "ge" => "≥",
+    "neq" | "ne" => "≠",
+    "approx" => "≈",
+    "equiv" => "≡",
+    "sim" => "∼",
+    "propto" => "∝",
+    "to" | "rightarrow" => "→",
+    "leftarrow" | "gets" => "←",
+    "Rightarrow" | "implies" => "⇒",
+    "Leftarrow" => "⇐",
+    "Leftrightarrow" | "iff" => "⇔",
+    "mapsto" => "↦",
+    "in" => "∈",
+    "notin" => "∉",
+    "ni" => "∋",
+    "subset" => "⊂",
+    "subseteq" => "⊆",
+    "supset" => "⊃",
+    "supseteq" => "⊇",
+    "cup" => "∪",
+    "cap" => "∩",
+    "setminus" => "∖",
+    "forall" => "∀",
+    "exists" => "∃",
+    "neg" | "lnot" => "¬",
+    "land" | "wedge" => "∧",
+    "lor" | "vee" => "∨",
+    "sum" => "∑",
+    "prod" => "∏",
+    "int" => "∫",
+    "iint" => "∬",
+    "oint" => "∮",
+    "ldots" | "dots" => "…",
+    "cdots" => "⋯",
+    "vdots" => "⋮",
+    "ddots" => "⋱",
+    "prime" => "′",
+    "langle" => "⟨",
+    "rangle" => "⟩",
+    "lfloor" => "⌊",
+    "rfloor" => "⌋",
+    "lceil" => "⌈",
+    "rceil" => "⌉",
+    "mid" => "∣",
+    "|" => "‖",
+    "perp" => "⊥",
+    "parallel" => "∥",
+    "angle" => "∠",
+    _ => return None,
+  };
+  Some((operator, false))
+}
+
+/// `{"x"}`, text of a MathML element in JSX
+fn text(value: &str) -> String {
+  format!("{{{}}}", serde_json::to_string(value).unwrap())
+}
+
+impl MathNode {
+  fn to_jsx(&self) -> String {
+    match self {
+      MathNode::Identifier(value) => format!("<mi>{}</mi>", text(value)),
+      MathNode::Number(value) => format!("<mn>{}</mn>", text(value)),
+      MathNode::Operator(value) => format!("<mo>{}</mo>", text(value)),
+      MathNode::Text(value) => format!("<mtext>{}</mtext>", text(value)),
+      MathNode::Row(nodes) => format!(
+        "<mrow>{}</mrow>",
+        nodes.iter().map(MathNode::to_jsx).collect::<String>()
+      ),
+      MathNode::Fraction(numerator, denominator) => format!(
+        "<mfrac>{}{}</mfrac>",
+        numerator.to_jsx(),
+        denominator.to_jsx()
+      ),
+      MathNode::Sqrt(radicand) => format!("<msqrt>{}</msqrt>", radicand.to_jsx()),
+      MathNode::Root(radicand, index) => {
+        format!("<mroot>{}{}</mroot>", radicand.to_jsx(), index.to_jsx())
+      }
+      MathNode::Scripts { base, sub, sup } => match (sub, sup) {
+        (Some(sub), Some(sup)) => format!(
+          "<msubsup>{}{}{}</msubsup>",
+          base.to_jsx(),
+          sub.to_jsx(),
+          sup.to_jsx()
+        ),
+        (Some(sub), None) => format!("<msub>{}{}</msub>", base.to_jsx(), sub.to_jsx()),
+        (None, Some(sup)) => format!("<msup>{}{}</msup>", base.to_jsx(), sup.to_jsx()),
+        (None, None) => base.to_jsx(),
+      },
+      MathNode::Space(width) => format!("<mspace width=\"{width}\" />"),
+      MathNode::Error(command) => {
+        format!("<merror><mtext>{}</mtext></merror>", text(command))
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn mathml(tex: &str) -> String {
+    let (open, _) = math_to_jsx(tex, false);
+    let start = open.find("<semantics>").unwrap() + "<semantics>".len();
+    let end = open.find("<annotation").unwrap();
+    open[start..end].to_string()
+  }
+
+  #[test]
+  fn test_math_to_jsx() {
+    assert_eq!(
+      mathml("x^2 + 1.5"),
+      "<mrow><msup><mi>{\"x\"}</mi><mn>{\"2\"}</mn></msup><mo>{\"+\"}</mo><mn>{\"1.5\"}</mn></mrow>"
+    );
+    assert_eq!(
+      mathml(r"\frac{a_1^n}{\sqrt[3]{\pi}}"),
+      "<mrow><mfrac><msubsup><mi>{\"a\"}</mi><mn>{\"1\"}</mn><mi>{\"n\"}</mi></msubsup>\
+       <mroot><mi>{\"π\"}</mi><mrow><mn>{\"3\"}</mn></mrow></mroot></mfrac></mrow>"
+    );
+    assert_eq!(
+      mathml(r"\sin x \leq \text{one}\,\foo"),
+      "<mrow><mi>{\"sin\"}</mi><mi>{\"x\"}</mi><mo>{\"≤\"}</mo><mtext>{\"one\"}</mtext>\
+       <mspace width=\"0.1667em\" /><merror><mtext>{\"\\\\foo\"}</mtext></merror></mrow>"
+    );
+    assert_eq!(
+      mathml(r"\left( x \right."),
+      "<mrow><mo>{\"(\"}</mo><mi>{\"x\"}</mi></mrow>"
+    );
+
+    let (open, close) = math_to_jsx("x", true);
+    assert!(open.starts_with(
+      "<span className=\"katex-display\"><span className=\"katex\"><math display=\"block\">"
+    ));
+    assert!(open.ends_with("<annotation encoding=\"application/x-tex\">"));
+    assert_eq!(close, "</annotation></semantics></math></span></span>");
+  }
+}
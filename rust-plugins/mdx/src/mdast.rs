@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use farmfe_core::{
+  module::ModuleId,
+  swc_ecma_ast::EsVersion,
+  swc_ecma_parser::{EsSyntax, Syntax},
+};
+use farmfe_toolkit::script::parse_module;
+use markdown::{mdast::Node, message::Message, Constructs, MdxSignal, ParseOptions};
+use mdxjs::MdxParseOptions;
+
+/// The markdown options `mdxjs::compile` parses with, so the tree we analyze
+/// has the same nodes as the one mdxjs renders.
+pub fn parse_options(options: &MdxParseOptions) -> ParseOptions {
+  let constructs = &options.constructs;
+  ParseOptions {
+    constructs: Constructs {
+      attention: constructs.attention,
+      autolink: false,
+      block_quote: constructs.block_quote,
+      character_escape: constructs.character_escape,
+      character_reference: constructs.character_reference,
+      code_fenced: constructs.code_fenced,
+      code_indented: false,
+      code_text: constructs.code_text,
+      definition: constructs.definition,
+      frontmatter: constructs.frontmatter,
+      gfm_autolink_literal: constructs.gfm_autolink_literal,
+      gfm_footnote_definition: constructs.gfm_footnote_definition,
+      gfm_label_start_footnote: constructs.gfm_label_start_footnote,
+      gfm_strikethrough: constructs.gfm_strikethrough,
+      gfm_table: constructs.gfm_table,
+      gfm_task_list_item: constructs.gfm_task_list_item,
+      hard_break_escape: constructs.hard_break_escape,
+      hard_break_trailing: constructs.hard_break_trailing,
+      html_flow: false,
+      html_text: false,
+      heading_atx: constructs.heading_atx,
+      heading_setext: constructs.heading_setext,
+      label_start_image: constructs.label_start_image,
+      label_start_link: constructs.label_start_link,
+      label_end: constructs.label_end,
+      list_item: constructs.list_item,
+      math_flow: constructs.math_flow,
+      math_text: constructs.math_text,
+      mdx_esm: true,
+      mdx_expression_flow: true,
+      mdx_expression_text: true,
+      mdx_jsx_flow: true,
+      mdx_jsx_text: true,
+      thematic_break: constructs.thematic_break,
+    },
+    gfm_strikethrough_single_tilde: options.gfm_strikethrough_single_tilde,
+    math_text_single_dollar: options.math_text_single_dollar,
+    // expressions are validated by `mdxjs::compile` itself
+    mdx_esm_parse: Some(Box::new(parse_esm)),
+    mdx_expression_parse: None,
+  }
+}
+
+/// markdown-rs only recognizes ESM with a parser. Errors are reported by
+/// `mdxjs::compile`, here a block that does not parse yet goes on after the
+/// blank line, as it does in mdxjs when swc stops at the end of the block.
+fn parse_esm(value: &str) -> MdxSignal {
+  let result = parse_module(
+    &ModuleId::from("esm.mdx"),
+    Arc::new(value.to_string()),
+    Syntax::Es(EsSyntax {
+      jsx: true,
+      ..Default::default()
+    }),
+    EsVersion::EsNext,
+  );
+  match result {
+    Ok(_) => MdxSignal::Ok,
+    Err(error) => MdxSignal::Eof(
+      error.to_string(),
+      Box::new("farm-mdx".to_string()),
+      Box::new("esm".to_string()),
+    ),
+  }
+}
+
+pub fn to_mdast(content: &str, options: &MdxParseOptions) -> Result<Node, Message> {
+  markdown::to_mdast(content, &parse_options(options))
+}
+
+/// The text content of a phrasing node, expressions are left out.
+pub fn to_text(node: &Node) -> String {
+  match node {
+    Node::Text(text) => text.value.clone(),
+    Node::InlineCode(code) => code.value.clone(),
+    Node::InlineMath(math) => math.value.clone(),
+    Node::MdxTextExpression(_) | Node::MdxFlowExpression(_) => String::new(),
+    node => node
+      .children()
+      .map(|children| children.iter().map(to_text).collect())
+      .unwrap_or_default(),
+  }
+}
+
+/// Visits every node in document order. Footnote definitions are rendered at
+/// the end of the document, so they are skipped to keep the order of the output.
+pub fn walk<'a>(node: &'a Node, visitor: &mut impl FnMut(&'a Node)) {
+  if let Node::FootnoteDefinition(_) = node {
+    return;
+  }
+  visitor(node);
+  if let Some(children) = node.children() {
+    for child in children {
+      walk(child, visitor);
+    }
+  }
+}
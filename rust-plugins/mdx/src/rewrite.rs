@@ -0,0 +1,613 @@
+use std::{
+  collections::{HashMap, HashSet, VecDeque},
+  sync::Arc,
+};
+
+use crate::{
+  frontmatter::is_valid_identifier,
+  links::{is_external_url, is_relative_url},
+  mdast::walk,
+};
+use farmfe_core::{
+  config::comments::CommentsConfig,
+  error::CompilationError,
+  module::ModuleId,
+  swc_common::{BytePos, Span, Spanned, DUMMY_SP},
+  swc_ecma_ast::*,
+  swc_ecma_parser::{EsSyntax, Syntax},
+};
+use farmfe_toolkit::{
+  script::{codegen_module, parse_module, CodeGenCommentsConfig, ParseScriptModuleResult},
+  sourcemap::SourceMapBuilder,
+  swc_ecma_codegen,
+  swc_ecma_visit::{VisitMut, VisitMutWith},
+};
+use markdown::mdast::{Node, Paragraph};
+
+/// Markdown elements are rendered through the `_components` object so they can
+/// be overridden, e.g. `_jsx(_components.h2, { children: "Install" })`.
+const COMPONENTS: &str = "_components";
+
+/// A markdown element in the compiled module, either a call of the JSX runtime
+/// (`_jsx(_components.h2, {...})`, `React.createElement(_components.h2, null)`)
+/// or a JSX element when the `jsx` option is set.
+pub enum Element<'a> {
+  Call(&'a mut CallExpr),
+  Jsx(&'a mut JSXOpeningElement),
+}
+
+impl Element<'_> {
+  /// The value of a string prop, e.g. `"./a.png"` for `src: "./a.png"`
+  pub fn str_prop(&self, name: &str) -> Option<String> {
+    match self {
+      Element::Call(call) => match &*call.args.get(1)?.expr {
+        Expr::Object(object) => object.props.iter().find_map(|prop| match prop {
+          PropOrSpread::Prop(prop) => match &**prop {
+            Prop::KeyValue(KeyValueProp { key, value }) if prop_key_name(key) == Some(name) => {
+              match &**value {
+                Expr::Lit(Lit::Str(s)) => Some(s.value.to_string()),
+                _ => None,
+              }
+            }
+            _ => None,
+          },
+          PropOrSpread::Spread(_) => None,
+        }),
+        _ => None,
+      },
+      Element::Jsx(opening) => opening.attrs.iter().find_map(|attr| match attr {
+        JSXAttrOrSpread::JSXAttr(JSXAttr {
+          value: Some(JSXAttrValue::Lit(Lit::Str(s))),
+          ..
+        }) if attr_name(attr) == Some(name) => Some(s.value.to_string()),
+        _ => None,
+      }),
+    }
+  }
+
+  pub fn set_prop(&mut self, name: &str, value: Expr) {
+    match self {
+      Element::Call(call) => {
+        let Some(props) = call.args.get_mut(1) else {
+          return;
+        };
+        if let Expr::Lit(Lit::Null(_)) = &*props.expr {
+          *props.expr = Expr::Object(ObjectLit {
+            span: DUMMY_SP,
+            props: vec![],
+          });
+        }
+        if let Expr::Object(object) = &mut *props.expr {
+          object.props.retain(|prop| prop_name(prop) != Some(name));
+          // before `children`, like mdxjs orders the props
+          object.props.insert(
+            0,
+            PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+              key: prop_key(name),
+              value: Box::new(value),
+            }))),
+          );
+        }
+      }
+      Element::Jsx(opening) => {
+        opening.attrs.retain(|attr| attr_name(attr) != Some(name));
+        let value = match value {
+          Expr::Lit(Lit::Str(s)) => JSXAttrValue::Lit(Lit::Str(s)),
+          value => JSXAttrValue::JSXExprContainer(JSXExprContainer {
+            span: DUMMY_SP,
+            expr: JSXExpr::Expr(Box::new(value)),
+          }),
+        };
+        opening.attrs.push(JSXAttrOrSpread::JSXAttr(JSXAttr {
+          span: DUMMY_SP,
+          name: JSXAttrName::Ident(IdentName::new(name.into(), DUMMY_SP)),
+          value: Some(value),
+        }));
+      }
+    }
+  }
+}
+
+fn prop_key(name: &str) -> PropName {
+  if is_valid_identifier(name) {
+    PropName::Ident(IdentName::new(name.into(), DUMMY_SP))
+  } else {
+    PropName::Str(str_lit(name))
+  }
+}
+
+fn prop_key_name(key: &PropName) -> Option<&str> {
+  match key {
+    PropName::Ident(ident) => Some(&ident.sym),
+    PropName::Str(s) => Some(&s.value),
+    _ => None,
+  }
+}
+
+fn prop_name(prop: &PropOrSpread) -> Option<&str> {
+  match prop {
+    PropOrSpread::Prop(prop) => match &**prop {
+      Prop::KeyValue(KeyValueProp { key, .. }) => prop_key_name(key),
+      _ => None,
+    },
+    PropOrSpread::Spread(_) => None,
+  }
+}
+
+fn attr_name(attr: &JSXAttrOrSpread) -> Option<&str> {
+  match attr {
+    JSXAttrOrSpread::JSXAttr(JSXAttr {
+      name: JSXAttrName::Ident(ident),
+      ..
+    }) => Some(&ident.sym),
+    _ => None,
+  }
+}
+
+pub fn str_lit(value: &str) -> Str {
+  Str {
+    span: DUMMY_SP,
+    value: value.into(),
+    raw: None,
+  }
+}
+
+/// The tag of `_components.h2`
+fn component_tag(expr: &Expr) -> Option<String> {
+  match expr {
+    Expr::Member(MemberExpr {
+      obj,
+      prop: MemberProp::Ident(prop),
+      ..
+    }) if matches!(&**obj, Expr::Ident(obj) if obj.sym == COMPONENTS) => Some(prop.sym.to_string()),
+    _ => None,
+  }
+}
+
+fn jsx_component_tag(name: &JSXElementName) -> Option<String> {
+  match name {
+    JSXElementName::JSXMemberExpr(JSXMemberExpr {
+      obj: JSXObject::Ident(obj),
+      prop,
+      ..
+    }) if obj.sym == COMPONENTS => Some(prop.sym.to_string()),
+    _ => None,
+  }
+}
+
+/// A markdown element of the mdast, matched against the `_components.*`
+/// elements of the compiled module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MdastElement {
+  pub tag: &'static str,
+  /// 1-indexed start of the node in the MDX source
+  pub line: usize,
+  pub column: usize,
+  /// `id` prop of headings
+  pub id: Option<String>,
+}
+
+const HEADINGS: [&str; 6] = ["h1", "h2", "h3", "h4", "h5", "h6"];
+
+impl MdastElement {
+  pub fn is_heading(&self) -> bool {
+    HEADINGS.contains(&self.tag)
+  }
+}
+
+/// Collects the nodes mdxjs renders as `_components.*` elements, in document order.
+pub fn collect_elements(mdast: &Node) -> VecDeque<MdastElement> {
+  let mut definitions = HashSet::new();
+  walk(mdast, &mut |node| {
+    if let Node::Definition(definition) = node {
+      definitions.insert(definition.identifier.as_str());
+    }
+  });
+  let mut elements = VecDeque::new();
+  collect(mdast, false, &definitions, &mut elements);
+  elements
+}
+
+/// `tight` is set for the children of a tight list item.
+fn collect(
+  node: &Node,
+  tight: bool,
+  definitions: &HashSet<&str>,
+  elements: &mut VecDeque<MdastElement>,
+) {
+  let tag = match node {
+    Node::Heading(heading) => Some(HEADINGS[heading.depth as usize - 1]),
+    Node::Paragraph(paragraph) if !tight && !is_unraveled(paragraph) => Some("p"),
+    Node::Blockquote(_) => Some("blockquote"),
+    Node::List(list) => Some(if list.ordered { "ol" } else { "ul" }),
+    Node::ListItem(_) => Some("li"),
+    Node::Code(_) | Node::Math(_) => Some("pre"),
+    Node::InlineCode(_) | Node::InlineMath(_) => Some("code"),
+    Node::Emphasis(_) => Some("em"),
+    Node::Strong(_) => Some("strong"),
+    Node::Delete(_) => Some("del"),
+    Node::Link(_) => Some("a"),
+    Node::LinkReference(link) if definitions.contains(link.identifier.as_str()) => Some("a"),
+    Node::Image(_) => Some("img"),
+    Node::ImageReference(image) if definitions.contains(image.identifier.as_str()) => Some("img"),
+    Node::ThematicBreak(_) => Some("hr"),
+    Node::Break(_) => Some("br"),
+    Node::Table(_) => Some("table"),
+    Node::TableRow(_) => Some("tr"),
+    Node::TableCell(_) => Some("td"),
+    Node::FootnoteReference(_) => Some("sup"),
+    _ => None,
+  };
+  if let (Some(tag), Some(position)) = (tag, node.position()) {
+    elements.push_back(MdastElement {
+      tag,
+      line: position.start.line,
+      column: position.start.column,
+      id: None,
+    });
+  }
+  let tight = match node {
+    // like mdxjs, a list is loose when any of its items is
+    Node::List(list) => {
+      !(list.spread
+        || list
+          .children
+          .iter()
+          .any(|item| matches!(item, Node::ListItem(item) if item.spread)))
+    }
+    Node::ListItem(_) => tight,
+    _ => false,
+  };
+  if let Some(children) = node.children() {
+    for child in children {
+      // rendered at the end of the document
+      if !matches!(child, Node::FootnoteDefinition(_)) {
+        collect(child, tight, definitions, elements);
+      }
+    }
+  }
+}
+
+/// mdxjs unwraps paragraphs that only hold JSX and expressions.
+fn is_unraveled(paragraph: &Paragraph) -> bool {
+  let mut has_mdx = false;
+  let only_mdx = paragraph.children.iter().all(|child| match child {
+    Node::MdxJsxTextElement(_) | Node::MdxTextExpression(_) => {
+      has_mdx = true;
+      true
+    }
+    Node::Text(text) => text.value.trim().is_empty(),
+    _ => false,
+  });
+  only_mdx && has_mdx
+}
+
+/// Edits the markdown elements of the compiled module and records where they
+/// come from in the MDX source. Elements are visited in document order, so
+/// they line up with the elements collected from the mdast.
+#[derive(Default)]
+pub struct MdxRewriter {
+  elements: VecDeque<MdastElement>,
+  /// Element mdxjs renders inside the previous one, e.g. the `code` of a `pre`
+  nested: Option<&'static str>,
+  /// Start of an element in the compiled module and in the MDX source (0-indexed)
+  origins: HashMap<BytePos, (u32, u32)>,
+  /// Turns relative image sources into imports, so they go through the asset pipeline
+  pub resolve_images: bool,
+  /// Opens links to other sites in a new tab
+  pub external_links: bool,
+  /// Sources of the imported images, the image at `index` is bound to `image_ident(index)`
+  images: Vec<String>,
+}
+
+fn image_ident(index: usize) -> Ident {
+  Ident::new_no_ctxt(format!("_mdxImage{index}").into(), DUMMY_SP)
+}
+
+impl MdxRewriter {
+  pub fn new(elements: VecDeque<MdastElement>) -> Self {
+    Self {
+      elements,
+      ..Default::default()
+    }
+  }
+
+  /// Takes the mdast element `tag` was rendered from. Elements mdxjs adds on
+  /// its own, like the `input` of task list items, have no match.
+  fn take_element(&mut self, tag: &str) -> Option<MdastElement> {
+    if self.nested.take() == Some(tag) {
+      return None;
+    }
+    let tag = if tag == "th" { "td" } else { tag };
+    let index = self
+      .elements
+      .iter()
+      .position(|element| element.tag == tag)?;
+    // elements before it were not rendered
+    self.elements.drain(..index);
+    let element = self.elements.pop_front()?;
+    self.nested = match tag {
+      "pre" => Some("code"),
+      "sup" => Some("a"),
+      _ => None,
+    };
+    Some(element)
+  }
+
+  fn rewrite(&mut self, tag: &str, span: Span, mut element: Element) {
+    if tag == "img" && self.resolve_images {
+      self.import_image(&mut element);
+    }
+    if tag == "a" && self.external_links {
+      Self::open_in_new_tab(&mut element);
+    }
+    let Some(mdast_element) = self.take_element(tag) else {
+      return;
+    };
+    if let Some(id) = &mdast_element.id {
+      element.set_prop("id", Expr::Lit(Lit::Str(str_lit(id))));
+    }
+    self.origins.insert(
+      span.lo,
+      (
+        mdast_element.line as u32 - 1,
+        mdast_element.column as u32 - 1,
+      ),
+    );
+  }
+}
+
+impl MdxRewriter {
+  /// `src: "./a.png"` becomes `src: _mdxImage0` and `./a.png` is imported later
+  fn import_image(&mut self, element: &mut Element) {
+    let Some(src) = element.str_prop("src").filter(|src| is_relative_url(src)) else {
+      return;
+    };
+    let index = match self.images.iter().position(|image| *image == src) {
+      Some(index) => index,
+      None => {
+        self.images.push(src);
+        self.images.len() - 1
+      }
+    };
+    element.set_prop("src", Expr::Ident(image_ident(index)));
+  }
+
+  /// `target="_blank"` and `rel="noopener noreferrer"` on a link to another site
+  fn open_in_new_tab(element: &mut Element) {
+    if element
+      .str_prop("href")
+      .is_some_and(|href| is_external_url(&href))
+    {
+      // props are set first, `target` ends up before `rel`
+      element.set_prop("rel", Expr::Lit(Lit::Str(str_lit("noopener noreferrer"))));
+      element.set_prop("target", Expr::Lit(Lit::Str(str_lit("_blank"))));
+    }
+  }
+
+  /// `import _mdxImage0 from "./a.png"` after the imports of the module
+  fn inject_image_imports(&self, module: &mut Module) {
+    let imports = self.images.iter().enumerate().map(|(index, src)| {
+      let src = if src.starts_with('.') {
+        src.clone()
+      } else {
+        format!("./{src}")
+      };
+      ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+        span: DUMMY_SP,
+        specifiers: vec![ImportSpecifier::Default(ImportDefaultSpecifier {
+          span: DUMMY_SP,
+          local: image_ident(index),
+        })],
+        src: Box::new(str_lit(&src)),
+        type_only: false,
+        with: None,
+        phase: Default::default(),
+      }))
+    });
+    let index = module
+      .body
+      .iter()
+      .take_while(|item| matches!(item, ModuleItem::ModuleDecl(ModuleDecl::Import(_))))
+      .count();
+    module.body.splice(index..index, imports);
+  }
+}
+
+impl VisitMut for MdxRewriter {
+  fn visit_mut_call_expr(&mut self, call: &mut CallExpr) {
+    if let Some(tag) = call.args.first().and_then(|arg| component_tag(&arg.expr)) {
+      let span = call.span;
+      self.rewrite(&tag, span, Element::Call(call));
+    }
+    call.visit_mut_children_with(self);
+  }
+
+  fn visit_mut_jsx_opening_element(&mut self, opening: &mut JSXOpeningElement) {
+    if let Some(tag) = jsx_component_tag(&opening.name) {
+      // `<` has no position in the output, `_components` does
+      let span = opening.name.span();
+      self.rewrite(&tag, span, Element::Jsx(opening));
+    }
+    opening.visit_mut_children_with(self);
+  }
+}
+
+/// Applies `rewriter` to the JavaScript mdxjs compiled. When `source` (the
+/// original MDX) is given, also returns a source map from the markdown
+/// elements back to it.
+pub fn rewrite_module(
+  resolved_path: &str,
+  code: String,
+  rewriter: &mut MdxRewriter,
+  source: Option<&str>,
+) -> Result<(String, Option<String>), CompilationError> {
+  let module_id = ModuleId::from(resolved_path);
+  let ParseScriptModuleResult {
+    mut ast,
+    comments,
+    source_map,
+  } = parse_module(
+    &module_id,
+    Arc::new(code),
+    Syntax::Es(EsSyntax {
+      jsx: true,
+      ..Default::default()
+    }),
+    EsVersion::EsNext,
+  )?;
+  ast.visit_mut_with(rewriter);
+  rewriter.inject_image_imports(&mut ast);
+  let mut mappings = vec![];
+  let code = codegen_module(
+    &ast,
+    source_map,
+    source.is_some().then_some(&mut mappings),
+    swc_ecma_codegen::Config::default(),
+    Some(CodeGenCommentsConfig {
+      comments: &comments,
+      // keeps `@jsxRuntime` and `@jsxImportSource` pragmas
+      config: &CommentsConfig::Bool(true),
+    }),
+  )
+  .map_err(|e| CompilationError::TransformError {
+    resolved_path: resolved_path.to_string(),
+    msg: e.to_string(),
+  })?;
+  let code = String::from_utf8(code).unwrap();
+
+  let source_map = source.map(|source| {
+    let mut builder = SourceMapBuilder::new(None);
+    let source_id = builder.add_source(resolved_path.to_string().into());
+    builder.set_source_contents(source_id, Some(source.to_string().into()));
+    for (pos, generated) in &mappings {
+      if let Some((line, column)) = rewriter.origins.get(pos) {
+        builder.add_raw(
+          generated.line,
+          generated.col,
+          *line,
+          *column,
+          Some(source_id),
+          None,
+          false,
+        );
+      }
+    }
+    let mut buf = vec![];
+    builder
+      .into_sourcemap()
+      .to_writer(&mut buf)
+      .expect("failed to write sourcemap");
+    String::from_utf8(buf).unwrap()
+  });
+  Ok((code, source_map))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rewrite(code: &str) -> String {
+    let heading = |tag, id: &str| MdastElement {
+      tag,
+      line: 1,
+      column: 1,
+      id: Some(id.to_string()),
+    };
+    let mut rewriter =
+      MdxRewriter::new(VecDeque::from(vec![heading("h1", "a"), heading("h2", "b")]));
+    rewrite_module("test.mdx", code.to_string(), &mut rewriter, None)
+      .unwrap()
+      .0
+  }
+
+  #[test]
+  fn test_heading_ids() {
+    let code = rewrite(
+      "_jsx(_components.h1, { children: [\"A\", _jsx(_components.code, { children: \"x\" })] });\n\
+       React.createElement(_components.h2, null, \"B\");\n\
+       _jsx(_components.h3, { children: \"C\" });\n",
+    );
+    assert!(code.contains("_jsx(_components.h1, {\n    id: \"a\",\n    children: ["));
+    assert!(code.contains("React.createElement(_components.h2, {\n    id: \"b\"\n}, \"B\")"));
+    assert!(code.contains("_jsx(_components.h3, {\n    children: \"C\"\n})"));
+
+    let code = rewrite("<_components.h1 id=\"old\">{\"A\"}</_components.h1>;");
+    assert!(code.contains("<_components.h1 id=\"a\">"));
+  }
+
+  #[test]
+  fn test_external_links() {
+    let mut rewriter = MdxRewriter::new(VecDeque::new());
+    rewriter.external_links = true;
+    let code = "_jsx(_components.a, { href: \"https://farmfe.org\", children: \"Farm\" });\n\
+                _jsx(_components.a, { href: \"./guide.mdx\", children: \"Guide\" });\n";
+    let code = rewrite_module("test.mdx", code.to_string(), &mut rewriter, None)
+      .unwrap()
+      .0;
+    assert!(code.contains(
+      "target: \"_blank\",\n    rel: \"noopener noreferrer\",\n    \
+       href: \"https://farmfe.org\",\n    children: \"Farm\""
+    ));
+    assert!(code.contains("href: \"./guide.mdx\",\n    children: \"Guide\"\n}"));
+  }
+
+  #[test]
+  fn test_collect_elements() {
+    let mdast = crate::mdast::to_mdast(
+      "# Title\n\n- a\n- *b*\n\n<Note>x</Note> {1}\n\n> [c][d]\n\n[d]: /d\n",
+      &Default::default(),
+    )
+    .unwrap();
+    let elements = collect_elements(&mdast)
+      .into_iter()
+      .map(|element| (element.tag, element.line, element.column))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      elements,
+      vec![
+        ("h1", 1, 1),
+        ("ul", 3, 1),
+        ("li", 3, 1),
+        ("li", 4, 1),
+        ("em", 4, 3),
+        ("blockquote", 8, 1),
+        ("p", 8, 3),
+        ("a", 8, 3),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_image_imports() {
+    let mut rewriter = MdxRewriter {
+      resolve_images: true,
+      ..Default::default()
+    };
+    let (code, _) = rewrite_module(
+      "test.mdx",
+      "import { jsx as _jsx } from \"react/jsx-runtime\";\n\
+       _jsx(_components.img, { src: \"./a.png\", alt: \"a\" });\n\
+       _jsx(_components.img, { src: \"img/b.png\" });\n\
+       _jsx(_components.img, { src: \"./a.png\" });\n\
+       _jsx(_components.img, { src: \"https://farmfe.org/c.png\" });\n\
+       <_components.img src=\"../d.png\" />;\n"
+        .to_string(),
+      &mut rewriter,
+      None,
+    )
+    .unwrap();
+    assert_eq!(
+      code,
+      "import { jsx as _jsx } from \"react/jsx-runtime\";\n\
+       import _mdxImage0 from \"./a.png\";\n\
+       import _mdxImage1 from \"./img/b.png\";\n\
+       import _mdxImage2 from \"../d.png\";\n\
+       _jsx(_components.img, {\n    src: _mdxImage0,\n    alt: \"a\"\n});\n\
+       _jsx(_components.img, {\n    src: _mdxImage1\n});\n\
+       _jsx(_components.img, {\n    src: _mdxImage0\n});\n\
+       _jsx(_components.img, {\n    src: \"https://farmfe.org/c.png\"\n});\n\
+       <_components.img src={_mdxImage2}/>;\n"
+    );
+  }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use farmfe_core::serde_json;
+use markdown::mdast::Node;
+use serde::{Deserialize, Serialize};
+
+use crate::mdast::{to_text, walk};
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SluggerKind {
+  /// Same ids as GitHub: lowercase, punctuation removed, spaces turned into `-`
+  #[default]
+  Github,
+  /// Transliterates to ASCII first, `Grüße` becomes `grusse`
+  Ascii,
+}
+
+/// `toc` option, `false` disables both the export and the heading ids
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum TocConfig {
+  Enabled(bool),
+  Options(TocOptions),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TocOptions {
+  pub min_depth: u8,
+  pub max_depth: u8,
+  pub slugger: SluggerKind,
+}
+
+impl Default for TocOptions {
+  fn default() -> Self {
+    Self {
+      min_depth: 1,
+      max_depth: 6,
+      slugger: SluggerKind::Github,
+    }
+  }
+}
+
+impl TocConfig {
+  pub fn options(&self) -> Option<TocOptions> {
+    match self {
+      TocConfig::Enabled(true) => Some(TocOptions::default()),
+      TocConfig::Enabled(false) => None,
+      TocConfig::Options(options) => Some(options.clone()),
+    }
+  }
+}
+
+/// Generates unique ids, repeated slugs get a `-1`, `-2`... suffix.
+#[derive(Default)]
+pub struct Slugger {
+  kind: SluggerKind,
+  occurrences: HashMap<String, usize>,
+}
+
+impl Slugger {
+  pub fn new(kind: SluggerKind) -> Self {
+    Self {
+      kind,
+      occurrences: HashMap::new(),
+    }
+  }
+
+  pub fn slug(&mut self, text: &str) -> String {
+    let base = match self.kind {
+      SluggerKind::Github => github_slug(text),
+      SluggerKind::Ascii => slug::slugify(text),
+    };
+    let mut slug = base.clone();
+    while let Some(count) = self.occurrences.get_mut(&slug) {
+      *count += 1;
+      slug = format!("{base}-{count}");
+    }
+    self.occurrences.insert(slug.clone(), 0);
+    slug
+  }
+}
+
+fn github_slug(text: &str) -> String {
+  text
+    .trim()
+    .to_lowercase()
+    .chars()
+    .filter_map(|c| match c {
+      ' ' => Some('-'),
+      '-' | '_' => Some(c),
+      c if c.is_alphanumeric() => Some(c),
+      _ => None,
+    })
+    .collect()
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TocEntry {
+  pub depth: u8,
+  pub text: String,
+  pub id: String,
+  pub children: Vec<TocEntry>,
+}
+
+pub struct Toc {
+  /// Ids of every heading, in document order
+  pub ids: Vec<String>,
+  pub entries: Vec<TocEntry>,
+}
+
+/// Collects the headings of `mdast`. Every heading gets an id so it can be linked
+/// to, only the ones within the depth range are listed in the toc.
+pub fn collect_toc(mdast: &Node, options: &TocOptions) -> Toc {
+  let mut slugger = Slugger::new(options.slugger);
+  let mut ids = vec![];
+  let mut headings = vec![];
+  walk(mdast, &mut |node| {
+    if let Node::Heading(heading) = node {
+      let text = heading.children.iter().map(to_text).collect::<String>();
+      let id = slugger.slug(&text);
+      ids.push(id.clone());
+      if (options.min_depth..=options.max_depth).contains(&heading.depth) {
+        headings.push((heading.depth, text.trim().to_string(), id));
+      }
+    }
+  });
+  Toc {
+    ids,
+    entries: nest(headings),
+  }
+}
+
+/// Nests each heading under the closest previous heading with a smaller depth.
+fn nest(headings: Vec<(u8, String, String)>) -> Vec<TocEntry> {
+  let mut roots: Vec<TocEntry> = vec![];
+  for (depth, text, id) in headings {
+    let entry = TocEntry {
+      depth,
+      text,
+      id,
+      children: vec![],
+    };
+    let mut siblings = &mut roots;
+    while siblings.last().is_some_and(|last| last.depth < depth) {
+      siblings = &mut siblings.last_mut().unwrap().children;
+    }
+    siblings.push(entry);
+  }
+  roots
+}
+
+pub fn stringify_toc(entries: &[TocEntry]) -> String {
+  format!(
+    "export const toc = {};\n",
+    serde_json::to_string(entries).unwrap()
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mdast::to_mdast;
+
+  #[test]
+  fn test_slugger() {
+    let mut slugger = Slugger::new(SluggerKind::Github);
+    assert_eq!(slugger.slug("Hello, World!"), "hello-world");
+    assert_eq!(slugger.slug("Hello World"), "hello-world-1");
+    assert_eq!(slugger.slug("hello-world-1"), "hello-world-1-1");
+    assert_eq!(slugger.slug("Grüße `code`"), "grüße-code");
+    assert_eq!(Slugger::new(SluggerKind::Ascii).slug("Grüße"), "grusse");
+  }
+
+  #[test]
+  fn test_collect_toc() {
+    let mdast = to_mdast(
+      "# Title\n\n## Install\n\n### npm {version}\n\n#### Deep\n\n## Usage `api`\n\n# Title\n",
+      &Default::default(),
+    )
+    .unwrap();
+    let toc = collect_toc(
+      &mdast,
+      &TocOptions {
+        max_depth: 3,
+        ..Default::default()
+      },
+    );
+    assert_eq!(
+      toc.ids,
+      vec!["title", "install", "npm", "deep", "usage-api", "title-1"]
+    );
+    assert_eq!(
+      serde_json::to_value(&toc.entries).unwrap(),
+      serde_json::json!([
+        { "depth": 1, "text": "Title", "id": "title", "children": [
+          { "depth": 2, "text": "Install", "id": "install", "children": [
+            { "depth": 3, "text": "npm", "id": "npm", "children": [] }
+          ] },
+          { "depth": 2, "text": "Usage api", "id": "usage-api", "children": [] }
+        ] },
+        { "depth": 1, "text": "Title", "id": "title-1", "children": [] }
+      ])
+    );
+  }
+}
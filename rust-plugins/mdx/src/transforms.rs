@@ -0,0 +1,379 @@
+use farmfe_core::serde_json;
+use lazy_static::lazy_static;
+use markdown::mdast::Node;
+use mdxjs::MdxParseOptions;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+  emoji::{emoji, SHORTCODE},
+  math::math_to_jsx,
+  mdast::{to_mdast, walk},
+  toc::{collect_toc, TocOptions},
+};
+
+lazy_static! {
+  /// `:::note`, `:::tip Title` or the closing `:::`, in a block quote too
+  static ref ADMONITION: Regex =
+    Regex::new(r"^([ \t>]*):::[ \t]*([a-zA-Z]+)?(?:[ \t]+(.*?))?[ \t]*$").unwrap();
+}
+
+/// An item of the `transforms` option
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Transform {
+  /// Tables, strikethrough, task lists, footnotes and autolink literals
+  Gfm,
+  /// An empty `<a className="heading-anchor">` to the id of each heading
+  HeadingAutolinks,
+  /// `target="_blank"` and `rel="noopener noreferrer"` on links to other sites
+  ExternalLinks,
+  /// `:tada:` shortcodes to emoji
+  Emoji,
+  /// `$...$` and `$$` blocks to MathML
+  Math,
+  /// `:::note` ... `:::` blocks to `<div className="admonition admonition-note">`
+  Admonitions,
+}
+
+/// The enabled transforms
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Transforms {
+  pub gfm: bool,
+  pub heading_autolinks: bool,
+  pub external_links: bool,
+  pub emoji: bool,
+  pub math: bool,
+  pub admonitions: bool,
+}
+
+impl Transforms {
+  pub fn new(transforms: &[Transform]) -> Self {
+    let enabled = |transform| transforms.contains(&transform);
+    Self {
+      gfm: enabled(Transform::Gfm),
+      heading_autolinks: enabled(Transform::HeadingAutolinks),
+      external_links: enabled(Transform::ExternalLinks),
+      emoji: enabled(Transform::Emoji),
+      math: enabled(Transform::Math),
+      admonitions: enabled(Transform::Admonitions),
+    }
+  }
+
+  /// Enables the constructs the transforms need
+  pub fn configure(&self, parse: &mut MdxParseOptions) {
+    let constructs = &mut parse.constructs;
+    if self.gfm {
+      constructs.gfm_autolink_literal = true;
+      constructs.gfm_footnote_definition = true;
+      constructs.gfm_label_start_footnote = true;
+      constructs.gfm_strikethrough = true;
+      constructs.gfm_table = true;
+      constructs.gfm_task_list_item = true;
+    }
+    if self.math {
+      constructs.math_flow = true;
+      constructs.math_text = true;
+    }
+  }
+
+  /// Applies the transforms that edit the document, the others are applied by
+  /// the parser and the rewriter. Every edit keeps the lines in place.
+  pub fn apply(&self, content: String, parse: &MdxParseOptions, ids: &TocOptions) -> String {
+    let mut content = content;
+    if self.admonitions {
+      content = edit(content, parse, admonitions);
+    }
+    if self.emoji {
+      content = edit(content, parse, replace_emoji);
+    }
+    if self.math {
+      content = edit(content, parse, render_math);
+    }
+    // last, the ids come from the final text of the headings
+    if self.heading_autolinks {
+      content = edit(content, parse, |content, mdast| {
+        autolink_headings(content, mdast, &collect_toc(mdast, ids).ids)
+      });
+    }
+    content
+  }
+}
+
+fn edit(
+  content: String,
+  parse: &MdxParseOptions,
+  transform: impl FnOnce(&str, &Node) -> String,
+) -> String {
+  // a document that does not parse is reported by `compile`
+  match to_mdast(&content, parse) {
+    Ok(mdast) => transform(&content, &mdast),
+    Err(_) => content,
+  }
+}
+
+/// Applies `edits` (byte ranges of `content` and their replacements), they
+/// must not overlap
+fn apply_edits(content: &str, mut edits: Vec<(usize, usize, String)>) -> String {
+  let mut result = content.to_string();
+  // from the end, so earlier offsets stay valid
+  edits.sort_by_key(|(start, ..)| std::cmp::Reverse(*start));
+  for (start, end, replacement) in edits {
+    result.replace_range(start..end, &replacement);
+  }
+  result
+}
+
+/// Every node, footnote definitions included
+fn visit_all<'a>(node: &'a Node, visitor: &mut impl FnMut(&'a Node)) {
+  visitor(node);
+  for child in node.children().into_iter().flatten() {
+    visit_all(child, visitor);
+  }
+}
+
+fn js_string(value: &str) -> String {
+  serde_json::to_string(value).unwrap()
+}
+
+/// Turns `:::note Title` and `:::` into the opening and closing tags of a
+/// `div`, so the content in between stays markdown. Unclosed blocks and lines
+/// in code are left alone.
+pub fn admonitions(content: &str, mdast: &Node) -> String {
+  let mut code = vec![];
+  visit_all(mdast, &mut |node| {
+    if let (Node::Code(_) | Node::Math(_), Some(position)) = (node, node.position()) {
+      code.push(position.start.offset..position.end.offset);
+    }
+  });
+
+  let mut edits = vec![];
+  let mut open = vec![];
+  let mut offset = 0;
+  for line in content.split_inclusive('\n') {
+    let start = offset;
+    offset += line.len();
+    let line = line.trim_end_matches(['\n', '\r']);
+    if code.iter().any(|range| range.contains(&start)) {
+      continue;
+    }
+    let Some(captures) = ADMONITION.captures(line) else {
+      continue;
+    };
+    let prefix = &captures[1];
+    match captures.get(2) {
+      Some(kind) => {
+        let kind = kind.as_str().to_lowercase();
+        let title = match captures.get(3) {
+          Some(title) => title.as_str().to_string(),
+          None => kind[..1].to_uppercase() + &kind[1..],
+        };
+        open.push((start, line.len(), prefix.to_string(), kind, title));
+      }
+      None if captures.get(3).is_none() => {
+        let Some((open_start, open_len, open_prefix, kind, title)) = open.pop() else {
+          continue;
+        };
+        edits.push((
+          open_start,
+          open_start + open_len,
+          format!(
+            "{open_prefix}<div className=\"admonition admonition-{kind}\">\
+             <p className=\"admonition-title\">{{{}}}</p>",
+            js_string(&title)
+          ),
+        ));
+        edits.push((start, start + line.len(), format!("{prefix}</div>")));
+      }
+      None => {}
+    }
+  }
+  apply_edits(content, edits)
+}
+
+/// Replaces the known `:name:` shortcodes of the text with their emoji
+pub fn replace_emoji(content: &str, mdast: &Node) -> String {
+  let mut edits = vec![];
+  visit_all(mdast, &mut |node| {
+    let (Node::Text(_), Some(position)) = (node, node.position()) else {
+      return;
+    };
+    let text = &content[position.start.offset..position.end.offset];
+    for captures in SHORTCODE.captures_iter(text) {
+      let Some(emoji) = emoji(&captures[1]) else {
+        continue;
+      };
+      let range = captures.get(0).unwrap().range();
+      edits.push((
+        position.start.offset + range.start,
+        position.start.offset + range.end,
+        emoji.to_string(),
+      ));
+    }
+  });
+  apply_edits(content, edits)
+}
+
+/// Replaces inline and block math with MathML. A block keeps its lines, its
+/// TeX is spread over them as the annotation.
+pub fn render_math(content: &str, mdast: &Node) -> String {
+  let mut edits = vec![];
+  visit_all(mdast, &mut |node| match node {
+    Node::InlineMath(math) => {
+      let Some(position) = &math.position else {
+        return;
+      };
+      let (open, close) = math_to_jsx(&math.value, false);
+      edits.push((
+        position.start.offset,
+        position.end.offset,
+        format!("{open}{{{}}}{close}", js_string(&math.value)),
+      ));
+    }
+    Node::Math(math) => {
+      let Some(position) = &math.position else {
+        return;
+      };
+      let source = &content[position.start.offset..position.end.offset];
+      let line_count = source.lines().count().max(1);
+      let (open, close) = math_to_jsx(&math.value, true);
+      let tex_lines = math.value.split('\n').collect::<Vec<_>>();
+      let mut lines = vec![open];
+      for (index, line) in tex_lines.iter().enumerate() {
+        let line = if index + 1 == tex_lines.len() {
+          line.to_string()
+        } else {
+          format!("{line}\n")
+        };
+        lines.push(format!("{{{}}}", js_string(&line)));
+      }
+      lines.push(close);
+      // an unclosed block has no closing line
+      while lines.len() > line_count {
+        let last = lines.pop().unwrap();
+        lines.last_mut().unwrap().push_str(&last);
+      }
+      lines.resize(line_count, String::new());
+
+      let prefix_width = position.start.column - 1;
+      let jsx = lines
+        .iter()
+        .zip(source.lines().chain(std::iter::repeat("")))
+        .enumerate()
+        .map(|(index, (jsx, line))| {
+          if index == 0 {
+            return jsx.clone();
+          }
+          // keep the `> ` or list indentation the block is nested in
+          let prefix = line.get(..prefix_width).unwrap_or(line);
+          format!("{prefix}{jsx}")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+      edits.push((position.start.offset, position.end.offset, jsx));
+    }
+    _ => {}
+  });
+  apply_edits(content, edits)
+}
+
+/// Appends an anchor to the id of each heading, `ids` in document order
+pub fn autolink_headings(content: &str, mdast: &Node, ids: &[String]) -> String {
+  let mut headings = vec![];
+  walk(mdast, &mut |node| {
+    if let Node::Heading(heading) = node {
+      headings.push(heading);
+    }
+  });
+  let edits = headings
+    .into_iter()
+    .zip(ids)
+    .filter_map(|(heading, id)| {
+      // after the text, `## Title ##` keeps its closing sequence
+      let end = heading.children.last()?.position()?.end.offset;
+      Some((
+        end,
+        end,
+        format!(
+          " <a href=\"#{id}\" className=\"heading-anchor\" aria-hidden=\"true\" tabIndex={{-1}} />"
+        ),
+      ))
+    })
+    .collect();
+  apply_edits(content, edits)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn transform(content: &str, parse: &MdxParseOptions, f: fn(&str, &Node) -> String) -> String {
+    f(content, &to_mdast(content, parse).unwrap())
+  }
+
+  #[test]
+  fn test_admonitions() {
+    let content = "Intro\n:::tip Read this\nSome *text*\n\n> :::warning\n> Careful\n> :::\n:::\n\n\
+                   ```md\n:::note\n```\n\n:::danger\n";
+    assert_eq!(
+      transform(content, &MdxParseOptions::default(), admonitions),
+      "Intro\n<div className=\"admonition admonition-tip\">\
+       <p className=\"admonition-title\">{\"Read this\"}</p>\nSome *text*\n\n\
+       > <div className=\"admonition admonition-warning\">\
+       <p className=\"admonition-title\">{\"Warning\"}</p>\n> Careful\n> </div>\n</div>\n\n\
+       ```md\n:::note\n```\n\n:::danger\n"
+    );
+  }
+
+  #[test]
+  fn test_replace_emoji() {
+    let content = "Done :tada: :not_an_emoji: `:tada:`\n\n# :rocket: Launch\n";
+    assert_eq!(
+      transform(content, &MdxParseOptions::default(), replace_emoji),
+      "Done 🎉 :not_an_emoji: `:tada:`\n\n# 🚀 Launch\n"
+    );
+  }
+
+  #[test]
+  fn test_render_math() {
+    let mut parse = MdxParseOptions::default();
+    Transforms::new(&[Transform::Math]).configure(&mut parse);
+    let content = "Euler $e^{i\\pi}$ here\n\n> $$\n> a\n> b\n> $$\n";
+    let result = transform(content, &parse, render_math);
+    let (open, close) = math_to_jsx("e^{i\\pi}", false);
+    assert!(result.starts_with(&format!("Euler {open}{{\"e^{{i\\\\pi}}\"}}{close} here\n")));
+    let (open, close) = math_to_jsx("a\nb", true);
+    assert!(result.ends_with(&format!(
+      "\n\n> {open}\n> {{\"a\\n\"}}\n> {{\"b\"}}\n> {close}\n"
+    )));
+    assert_eq!(result.lines().count(), content.lines().count());
+  }
+
+  #[test]
+  fn test_autolink_headings() {
+    let content = "# Hello *world*\n\nText\n\nGuide ##\n=====\n\n## Hello world ##\n";
+    let mdast = to_mdast(content, &MdxParseOptions::default()).unwrap();
+    let ids = collect_toc(&mdast, &TocOptions::default()).ids;
+    let anchor = |id| {
+      format!(
+        " <a href=\"#{id}\" className=\"heading-anchor\" aria-hidden=\"true\" tabIndex={{-1}} />"
+      )
+    };
+    assert_eq!(
+      autolink_headings(content, &mdast, &ids),
+      format!(
+        "# Hello *world*{}\n\nText\n\nGuide ##{}\n=====\n\n## Hello world{} ##\n",
+        anchor("hello-world"),
+        anchor("guide-"),
+        anchor("hello-world-1")
+      )
+    );
+  }
+
+  #[test]
+  fn test_configure() {
+    let mut parse = MdxParseOptions::default();
+    Transforms::new(&[Transform::Gfm]).configure(&mut parse);
+    assert_eq!(parse, MdxParseOptions::gfm());
+  }
+}
@@ -5,4 +5,6 @@ export interface IPluginOptions {
   functions?: string[];
   sourceMap?: boolean;
   debugger?: boolean;
+  sideEffects?: string[];
+  defines?: Record<string, unknown>;
 }
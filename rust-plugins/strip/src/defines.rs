@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+
+use farmfe_core::{
+  serde_json::Value,
+  swc_common::{util::take::Take, DUMMY_SP},
+  swc_ecma_ast::*,
+};
+use farmfe_toolkit::swc_ecma_visit::{Visit, VisitWith};
+
+/// The expression of a value of `defines`, strings are string literals.
+/// The module is printed without a fixer pass, so objects are parenthesized
+/// for `() => CONFIG` or `CONFIG.a;` not to read as a block, and negative
+/// numbers for `NEG ** 2` or `NEG.toFixed(1)` to keep their meaning.
+pub fn value_to_expr(value: &Value) -> Expr {
+  let negative = value.as_f64().is_some_and(|value| value.is_sign_negative());
+  match value {
+    Value::Object(_) => paren_expr(json_expr(value)),
+    Value::Number(_) if negative => paren_expr(json_expr(value)),
+    value => json_expr(value),
+  }
+}
+
+fn paren_expr(expr: Expr) -> Expr {
+  Expr::Paren(ParenExpr {
+    span: DUMMY_SP,
+    expr: Box::new(expr),
+  })
+}
+
+fn json_expr(value: &Value) -> Expr {
+  match value {
+    Value::Null => Expr::Lit(Lit::Null(Null { span: DUMMY_SP })),
+    Value::Bool(value) => bool_expr(*value),
+    Value::Number(value) => Expr::Lit(Lit::Num(Number {
+      span: DUMMY_SP,
+      value: value.as_f64().unwrap_or(f64::NAN),
+      raw: None,
+    })),
+    Value::String(value) => str_expr(value),
+    Value::Array(items) => Expr::Array(ArrayLit {
+      span: DUMMY_SP,
+      elems: items
+        .iter()
+        .map(|item| {
+          Some(ExprOrSpread {
+            spread: None,
+            expr: Box::new(json_expr(item)),
+          })
+        })
+        .collect(),
+    }),
+    Value::Object(map) => Expr::Object(ObjectLit {
+      span: DUMMY_SP,
+      props: map
+        .iter()
+        .map(|(key, value)| {
+          PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+            key: PropName::Str(Str {
+              span: DUMMY_SP,
+              value: key.as_str().into(),
+              raw: None,
+            }),
+            value: Box::new(json_expr(value)),
+          })))
+        })
+        .collect(),
+    }),
+  }
+}
+
+fn bool_expr(value: bool) -> Expr {
+  Expr::Lit(Lit::Bool(Bool {
+    span: DUMMY_SP,
+    value,
+  }))
+}
+
+fn str_expr(value: &str) -> Expr {
+  Expr::Lit(Lit::Str(Str {
+    span: DUMMY_SP,
+    value: value.into(),
+    raw: None,
+  }))
+}
+
+/// A primitive whose value is known at build time
+#[derive(Debug, PartialEq)]
+pub enum Literal<'a> {
+  Str(&'a str),
+  Num(f64),
+  Bool(bool),
+  Null,
+  Undefined,
+}
+
+impl Literal<'_> {
+  pub fn truthy(&self) -> bool {
+    match self {
+      Literal::Str(value) => !value.is_empty(),
+      Literal::Num(value) => *value != 0.0 && !value.is_nan(),
+      Literal::Bool(value) => *value,
+      Literal::Null | Literal::Undefined => false,
+    }
+  }
+
+  fn type_of(&self) -> &'static str {
+    match self {
+      Literal::Str(_) => "string",
+      Literal::Num(_) => "number",
+      Literal::Bool(_) => "boolean",
+      Literal::Null => "object",
+      Literal::Undefined => "undefined",
+    }
+  }
+
+  fn is_nullish(&self) -> bool {
+    matches!(self, Literal::Null | Literal::Undefined)
+  }
+
+  /// `==` or `===`, none when a loose comparison would convert the operands
+  fn equals(&self, other: &Literal, strict: bool) -> Option<bool> {
+    match (self, other) {
+      (Literal::Str(a), Literal::Str(b)) => Some(a == b),
+      (Literal::Num(a), Literal::Num(b)) => Some(a == b),
+      (Literal::Bool(a), Literal::Bool(b)) => Some(a == b),
+      (a, b) if a.is_nullish() && b.is_nullish() => Some(!strict || a == b),
+      (a, b) if strict || a.is_nullish() || b.is_nullish() => Some(false),
+      _ => None,
+    }
+  }
+}
+
+pub fn literal(expr: &Expr) -> Option<Literal<'_>> {
+  match expr {
+    Expr::Paren(ParenExpr { expr, .. }) => literal(expr),
+    Expr::Lit(Lit::Str(value)) => Some(Literal::Str(&value.value)),
+    Expr::Lit(Lit::Num(value)) => Some(Literal::Num(value.value)),
+    Expr::Lit(Lit::Bool(value)) => Some(Literal::Bool(value.value)),
+    Expr::Lit(Lit::Null(_)) => Some(Literal::Null),
+    Expr::Unary(UnaryExpr {
+      op: UnaryOp::Void,
+      arg,
+      ..
+    }) if literal(arg).is_some() => Some(Literal::Undefined),
+    _ => None,
+  }
+}
+
+/// Folds `expr` when the operands it depends on are literals, e.g. `!false`,
+/// `"production" !== "production"` or `false && a`
+pub fn fold_expr(expr: &mut Expr) {
+  let folded = match expr {
+    Expr::Paren(ParenExpr { expr, .. }) if literal(expr).is_some() => Some(*expr.take()),
+    Expr::Unary(UnaryExpr {
+      op: UnaryOp::Bang,
+      arg,
+      ..
+    }) => literal(arg).map(|value| bool_expr(!value.truthy())),
+    Expr::Unary(UnaryExpr {
+      op: UnaryOp::TypeOf,
+      arg,
+      ..
+    }) => literal(arg).map(|value| str_expr(value.type_of())),
+    Expr::Bin(BinExpr {
+      op, left, right, ..
+    }) => match op {
+      BinaryOp::LogicalAnd | BinaryOp::LogicalOr | BinaryOp::NullishCoalescing => literal(left)
+        .map(|value| match op {
+          BinaryOp::LogicalAnd => !value.truthy(),
+          BinaryOp::LogicalOr => value.truthy(),
+          _ => !value.is_nullish(),
+        })
+        .map(|short_circuits| {
+          if short_circuits {
+            *left.take()
+          } else {
+            *right.take()
+          }
+        }),
+      BinaryOp::EqEq | BinaryOp::NotEq | BinaryOp::EqEqEq | BinaryOp::NotEqEq => {
+        let strict = matches!(op, BinaryOp::EqEqEq | BinaryOp::NotEqEq);
+        let negated = matches!(op, BinaryOp::NotEq | BinaryOp::NotEqEq);
+        match (literal(left), literal(right)) {
+          (Some(a), Some(b)) => a.equals(&b, strict).map(|eq| bool_expr(eq != negated)),
+          _ => None,
+        }
+      }
+      _ => None,
+    },
+    Expr::Cond(CondExpr {
+      test, cons, alt, ..
+    }) => literal(test).map(|value| {
+      if value.truthy() {
+        *cons.take()
+      } else {
+        *alt.take()
+      }
+    }),
+    _ => None,
+  };
+  if let Some(folded) = folded {
+    *expr = folded;
+  }
+}
+
+/// The names the module declares, ambient `declare` declarations left out.
+/// Names are collected regardless of their scope.
+#[derive(Default)]
+pub struct DeclaredNames {
+  pub names: HashSet<String>,
+}
+
+impl Visit for DeclaredNames {
+  fn visit_binding_ident(&mut self, ident: &BindingIdent) {
+    self.names.insert(ident.sym.to_string());
+  }
+
+  fn visit_fn_decl(&mut self, decl: &FnDecl) {
+    if !decl.declare {
+      self.names.insert(decl.ident.sym.to_string());
+      decl.visit_children_with(self);
+    }
+  }
+
+  fn visit_class_decl(&mut self, decl: &ClassDecl) {
+    if !decl.declare {
+      self.names.insert(decl.ident.sym.to_string());
+      decl.visit_children_with(self);
+    }
+  }
+
+  fn visit_var_decl(&mut self, decl: &VarDecl) {
+    if !decl.declare {
+      decl.visit_children_with(self);
+    }
+  }
+
+  fn visit_ts_module_decl(&mut self, _: &TsModuleDecl) {}
+
+  fn visit_import_decl(&mut self, import: &ImportDecl) {
+    for specifier in &import.specifiers {
+      let local = match specifier {
+        ImportSpecifier::Named(named) => &named.local,
+        ImportSpecifier::Default(default) => &default.local,
+        ImportSpecifier::Namespace(namespace) => &namespace.local,
+      };
+      self.names.insert(local.sym.to_string());
+    }
+  }
+}
+
+/// The `var` bindings of a statement, they are hoisted to the function and
+/// outlive the statement when it is removed
+#[derive(Default)]
+pub struct VarNames {
+  pub names: Vec<Ident>,
+  /// Also collects function declarations, which sloppy-mode code hoists out
+  /// of blocks (Annex B)
+  pub functions: bool,
+}
+
+impl Visit for VarNames {
+  fn visit_binding_ident(&mut self, ident: &BindingIdent) {
+    self.names.push(ident.id.clone());
+  }
+
+  fn visit_var_decl(&mut self, decl: &VarDecl) {
+    if decl.kind == VarDeclKind::Var && !decl.declare {
+      for declarator in &decl.decls {
+        declarator.name.visit_with(self);
+      }
+    }
+  }
+
+  fn visit_decl(&mut self, decl: &Decl) {
+    match decl {
+      Decl::Var(decl) => decl.visit_with(self),
+      Decl::Fn(decl) if self.functions && !decl.declare => self.names.push(decl.ident.clone()),
+      _ => {}
+    }
+  }
+
+  // default values and initializers declare nothing outside of functions
+  fn visit_expr(&mut self, _: &Expr) {}
+
+  fn visit_catch_clause(&mut self, clause: &CatchClause) {
+    clause.body.visit_with(self);
+  }
+}
@@ -1,26 +1,30 @@
 #![deny(clippy::all)]
 
+mod defines;
+
 use regex::Regex;
-use std::{error::Error, sync::Arc};
+use std::{collections::HashMap, error::Error, sync::Arc};
 
 use farmfe_core::{
   config::{config_regex::ConfigRegex, Config},
   context::{create_swc_source_map, CompilationContext},
-  error::Result as HookResult,
+  error::{CompilationError, Result as HookResult},
   module::ModuleType,
   plugin::{Plugin, PluginTransformHookParam, PluginTransformHookResult},
   serde_json,
-  swc_common::{source_map::DefaultSourceMapGenConfig, SyntaxContext, DUMMY_SP},
+  swc_common::{source_map::DefaultSourceMapGenConfig, util::take::Take, DUMMY_SP},
   swc_ecma_ast::*,
   swc_ecma_parser::{Syntax, TsSyntax},
 };
 use farmfe_macro_plugin::farm_plugin;
 
+use defines::{fold_expr, literal, value_to_expr, DeclaredNames, VarNames};
+
 use farmfe_toolkit::{
   plugin_utils::path_filter::PathFilter,
   script::{codegen_module, parse_module, CodeGenCommentsConfig, ParseScriptModuleResult},
   swc_ecma_codegen,
-  swc_ecma_visit::{VisitMut, VisitMutWith},
+  swc_ecma_visit::{Visit, VisitMut, VisitMutWith, VisitWith},
 };
 
 #[derive(serde::Deserialize, Clone)]
@@ -32,6 +36,12 @@ pub struct Options {
   pub functions: Option<Vec<String>>,
   pub source_map: Option<bool>,
   pub debugger: Option<bool>,
+  /// Import sources of modules with side effects, their imports are kept even
+  /// when stripping leaves them unused
+  pub side_effects: Option<Vec<ConfigRegex>>,
+  /// Globals replaced by their value, e.g. `{ "__DEV__": false }`, the
+  /// branches they leave dead are removed
+  pub defines: Option<HashMap<String, serde_json::Value>>,
 }
 
 const PLUGIN_NAME: &str = "FarmPulginStrip";
@@ -69,35 +79,39 @@ impl Plugin for FarmPulginStrip {
       return Ok(None);
     }
     let options = self.options.clone();
-    let include = options.include.unwrap_or(vec![]);
+    let include = options.include.unwrap_or_default();
     let exclude = options
       .exclude
       .unwrap_or_else(|| vec![ConfigRegex::new("node_modules/.*")]);
     let filter = PathFilter::new(&include, &exclude);
-    if !filter.execute(&param.resolved_path) {
+    if !filter.execute(param.resolved_path) {
       return Ok(None);
     }
 
-    let source_map = match options.source_map {
-      Some(s) => s != false,
-      None => false,
-    };
+    let source_map = options.source_map.unwrap_or(false);
 
-    let remove_debugger_statements = match options.debugger {
-      Some(s) => s != false,
-      None => false,
-    };
+    let remove_debugger_statements = options.debugger.unwrap_or(false);
+
+    let labels = options.labels.unwrap_or_default();
+
+    let functions: Vec<String> = options.functions.unwrap_or_default();
 
-    let labels = options.labels.unwrap_or(vec![]);
+    let side_effects = options.side_effects.unwrap_or_default();
 
-    let functions: Vec<String> = options.functions.unwrap_or(vec![]);
+    let defines: HashMap<String, Expr> = options
+      .defines
+      .unwrap_or_default()
+      .iter()
+      .map(|(key, value)| (key.clone(), value_to_expr(value)))
+      .collect();
 
-    let mut labels_patterns: Vec<String> = labels.iter().map(|l| format!(r"{}\s*:", l)).collect();
+    let mut labels_patterns: Vec<String> = labels.iter().map(|l| format!(r"{l}\s*:")).collect();
     let mut first_pass = functions.clone();
     first_pass.append(&mut labels_patterns);
     if remove_debugger_statements {
       first_pass.push("debugger\\b".to_string())
     }
+    first_pass.extend(defines.keys().map(|key| regex::escape(key)));
 
     let first_pass_filter = if !first_pass.is_empty() {
       Box::new(move |code: &str| create_first_pass_regex(&first_pass).unwrap().is_match(code))
@@ -114,7 +128,7 @@ impl Plugin for FarmPulginStrip {
       create_swc_source_map(&param.resolved_path.into(), Arc::new(param.content.clone()));
     let ParseScriptModuleResult {
       mut ast, comments, ..
-    } = match parse_module(
+    } = parse_module(
       &param.module_id.clone().into(),
       Arc::new(param.content.clone()),
       Syntax::Typescript(TsSyntax {
@@ -125,16 +139,22 @@ impl Plugin for FarmPulginStrip {
         disallow_ambiguous_jsx_like: true,
       }),
       EsVersion::EsNext,
-    ) {
-      Ok(res) => res,
-      Err(err) => {
-        println!("{}", err.to_string());
-        panic!("Parse {} failed. See error details above.", param.module_id);
-      }
-    };
+    )
+    .map_err(|err| CompilationError::ParseError {
+      resolved_path: param.resolved_path.to_string(),
+      msg: match err {
+        CompilationError::ParseError { msg, .. } => msg,
+        err => err.to_string(),
+      },
+    })?;
     let re_functions_regex = create_regex_from_list(&functions).unwrap();
-    let mut remover = StripCode::new(labels, re_functions_regex, remove_debugger_statements);
-    ast.visit_mut_with(&mut remover);
+    let mut remover = StripCode::new(
+      labels,
+      re_functions_regex,
+      remove_debugger_statements,
+      defines,
+    );
+    strip_module(&mut ast, &mut remover, &side_effects);
     let mut src_map = vec![];
     let transformed_content = codegen_module(
       &ast,
@@ -168,75 +188,322 @@ impl Plugin for FarmPulginStrip {
   }
 }
 
+/// Strips `ast`, then removes the imports that were only used by the stripped code
+fn strip_module(ast: &mut Module, remover: &mut StripCode, side_effects: &[ConfigRegex]) {
+  let before = count_references(ast);
+  // defines replace globals, a name the module declares is left alone
+  let mut declared = DeclaredNames::default();
+  ast.visit_with(&mut declared);
+  remover.defines.retain(|key, _| {
+    let name = key.split('.').next().unwrap_or(key);
+    !declared.names.contains(name)
+  });
+  remover.sloppy = !is_strict(ast);
+  ast.visit_mut_with(remover);
+  remove_unused_imports(ast, &before, side_effects);
+}
+
 struct StripCode {
   labels: Vec<String>,
   re_functions_regex: Regex,
   should_re_debugger: bool,
+  defines: HashMap<String, Expr>,
+  /// Whether the module is sloppy-mode code, whose block-level functions are
+  /// also `var` bindings
+  sloppy: bool,
 }
 
 impl StripCode {
-  fn new(labels: Vec<String>, re_functions_regex: Regex, should_re_debugger: bool) -> Self {
+  fn new(
+    labels: Vec<String>,
+    re_functions_regex: Regex,
+    should_re_debugger: bool,
+    defines: HashMap<String, Expr>,
+  ) -> Self {
     StripCode {
       labels,
       re_functions_regex,
       should_re_debugger,
+      defines,
+      sloppy: false,
+    }
+  }
+
+  /// The value of `expr` when it is one of `defines`, e.g. `process.env.NODE_ENV`
+  fn define(&self, expr: &Expr) -> Option<&Expr> {
+    if self.defines.is_empty() {
+      return None;
+    }
+    let key = match expr {
+      Expr::Ident(ident) => Some(ident.sym.to_string()),
+      _ => flatten(expr),
+    };
+    key.and_then(|key| self.defines.get(&key))
+  }
+
+  /// Whether `expr` is a call of one of `functions`, e.g. `console.log(x)`
+  fn is_stripped_call(&self, expr: &Expr) -> bool {
+    match expr {
+      Expr::Paren(ParenExpr { expr, .. }) => self.is_stripped_call(expr),
+      Expr::Call(CallExpr {
+        callee: Callee::Expr(callee_expr),
+        ..
+      }) => flatten(callee_expr).is_some_and(|callee| self.re_functions_regex.is_match(&callee)),
+      _ => false,
+    }
+  }
+
+  /// Whether the statement is removed entirely
+  fn is_stripped_stmt(&self, stmt: &Stmt) -> bool {
+    match stmt {
+      Stmt::Debugger(_) => self.should_re_debugger,
+      Stmt::Labeled(LabeledStmt { label, .. }) => self.labels.contains(&label.sym.to_string()),
+      Stmt::Expr(ExprStmt { expr, .. }) => self.is_stripped_call(expr),
+      _ => false,
+    }
+  }
+
+  /// Strips `stmt` into the statements replacing it: none when it is removed,
+  /// or the branch taken by an `if` whose test is known
+  fn strip_stmt(&mut self, mut stmt: Stmt) -> Vec<Stmt> {
+    if self.is_stripped_stmt(&stmt) {
+      return vec![];
+    }
+    // directives such as `"use strict"` are literals from the start
+    let was_literal = matches!(&stmt, Stmt::Expr(ExprStmt { expr, .. }) if literal(expr).is_some());
+    stmt.visit_mut_children_with(self);
+    // without defines the code is left as written
+    if self.defines.is_empty() {
+      return vec![stmt];
+    }
+    match stmt {
+      Stmt::If(IfStmt {
+        test, cons, alt, ..
+      }) if literal(&test).is_some() => {
+        let (taken, dead) = if literal(&test).is_some_and(|value| value.truthy()) {
+          (Some(*cons), alt.map(|alt| *alt))
+        } else {
+          (alt.map(|alt| *alt), Some(*cons))
+        };
+        let mut stmts = dead
+          .map(|dead| hoisted_vars(dead, self.sloppy))
+          .unwrap_or_default();
+        stmts.extend(taken.map(unwrap_block).unwrap_or_default());
+        stmts
+      }
+      Stmt::Expr(ExprStmt { expr, .. }) if !was_literal && literal(&expr).is_some() => vec![],
+      stmt => vec![stmt],
     }
   }
 }
 
 impl VisitMut for StripCode {
-  fn visit_mut_module(&mut self, module: &mut Module) {
-    let should_remove_debugger = self.should_re_debugger;
-    let labels_to_remove = &self.labels;
-    module.body.retain_mut(|item| match item {
-      ModuleItem::Stmt(Stmt::Debugger(..)) => !should_remove_debugger,
-      ModuleItem::Stmt(Stmt::Labeled(LabeledStmt { label, .. })) => {
-        !labels_to_remove.contains(&label.sym.to_string())
-      }
-      _ => true,
-    });
-    module.visit_mut_children_with(self);
+  fn visit_mut_module_items(&mut self, items: &mut Vec<ModuleItem>) {
+    *items = items
+      .take()
+      .into_iter()
+      .flat_map(|mut item| match item {
+        ModuleItem::Stmt(stmt) => self
+          .strip_stmt(stmt)
+          .into_iter()
+          .map(ModuleItem::Stmt)
+          .collect(),
+        _ => {
+          item.visit_mut_with(self);
+          vec![item]
+        }
+      })
+      .collect();
   }
 
   fn visit_mut_stmts(&mut self, stmts: &mut Vec<Stmt>) {
-    let should_remove_debugger = self.should_re_debugger;
-    let labels_to_remove = &self.labels;
-    stmts.retain_mut(|stmt| match stmt {
-      Stmt::Debugger(_) => !should_remove_debugger,
-      Stmt::Labeled(LabeledStmt { label, .. }) => {
-        !labels_to_remove.contains(&label.sym.to_string())
-      }
-      _ => true,
-    });
-    for stmt in stmts.iter_mut() {
-      stmt.visit_mut_with(self);
-    }
+    *stmts = stmts
+      .take()
+      .into_iter()
+      .flat_map(|stmt| self.strip_stmt(stmt))
+      .collect();
+  }
+
+  fn visit_mut_stmt(&mut self, stmt: &mut Stmt) {
+    // the body of `if (a) console.log(a)` can not be removed, it becomes a block
+    let mut stmts = self.strip_stmt(stmt.take());
+    *stmt = if stmts.len() == 1 {
+      stmts.remove(0)
+    } else {
+      Stmt::Block(BlockStmt {
+        span: DUMMY_SP,
+        stmts,
+        ..Default::default()
+      })
+    };
   }
 
   fn visit_mut_expr(&mut self, e: &mut Expr) {
-    if let Expr::Call(CallExpr { callee, .. }) = e {
-      if let Callee::Expr(callee_expr) = callee {
-        if let Some(flatten_callee) = flatten(&callee_expr) {
-          if self.re_functions_regex.is_match(&flatten_callee) {
-            *e = *void_expr();
-            return;
-          }
-        }
-      }
+    if self.is_stripped_call(e) {
+      *e = *void_expr();
+      return;
+    }
+    if let Some(value) = self.define(e) {
+      *e = value.clone();
+      return;
     }
     e.visit_mut_children_with(self);
+    if !self.defines.is_empty() {
+      fold_expr(e);
+    }
+  }
+
+  fn visit_mut_prop(&mut self, prop: &mut Prop) {
+    // `{ __DEV__ }`
+    if let Prop::Shorthand(ident) = prop {
+      if let Some(value) = self.defines.get(ident.sym.as_ref()) {
+        *prop = Prop::KeyValue(KeyValueProp {
+          key: PropName::Ident(ident.clone().into()),
+          value: Box::new(value.clone()),
+        });
+        return;
+      }
+    }
+    prop.visit_mut_children_with(self);
+  }
+}
+
+/// Whether the module is strict-mode code: an ES module or a script starting
+/// with a `"use strict"` directive
+fn is_strict(ast: &Module) -> bool {
+  ast
+    .body
+    .iter()
+    .any(|item| matches!(item, ModuleItem::ModuleDecl(_)))
+    || ast
+      .body
+      .iter()
+      .map_while(|item| match item {
+        ModuleItem::Stmt(Stmt::Expr(ExprStmt { expr, .. })) => match &**expr {
+          Expr::Lit(Lit::Str(value)) => Some(value),
+          _ => None,
+        },
+        _ => None,
+      })
+      .any(|value| value.value == *"use strict")
+}
+
+/// `var a, b;` for the `var` bindings of a removed statement, and in sloppy
+/// mode for the functions it declares in blocks
+fn hoisted_vars(stmt: Stmt, sloppy: bool) -> Vec<Stmt> {
+  let mut vars = VarNames {
+    functions: sloppy,
+    ..Default::default()
+  };
+  stmt.visit_with(&mut vars);
+  if vars.names.is_empty() {
+    return vec![];
+  }
+  vec![Stmt::Decl(Decl::Var(Box::new(VarDecl {
+    span: DUMMY_SP,
+    kind: VarDeclKind::Var,
+    decls: vars
+      .names
+      .into_iter()
+      .map(|name| VarDeclarator {
+        span: DUMMY_SP,
+        name: Pat::Ident(name.into()),
+        init: None,
+        definite: false,
+      })
+      .collect(),
+    ..Default::default()
+  })))]
+}
+
+/// The statements of a block, unless it scopes `let`, `const`, a class or a function
+fn unwrap_block(stmt: Stmt) -> Vec<Stmt> {
+  match stmt {
+    Stmt::Block(BlockStmt { stmts, .. })
+      if stmts.iter().all(|stmt| match stmt {
+        Stmt::Decl(Decl::Var(decl)) => decl.kind == VarDeclKind::Var,
+        Stmt::Decl(_) => false,
+        _ => true,
+      }) =>
+    {
+      stmts
+    }
+    stmt => vec![stmt],
   }
 }
 
+/// `void 0`
 fn void_expr() -> Box<Expr> {
-  Box::new(Expr::Ident(Ident {
-    sym: "(void 0)".into(),
+  Box::new(Expr::Unary(UnaryExpr {
     span: DUMMY_SP,
-    optional: false,
-    ctxt: SyntaxContext::empty(),
+    op: UnaryOp::Void,
+    arg: Box::new(Expr::Lit(Lit::Num(Number {
+      span: DUMMY_SP,
+      value: 0.0,
+      raw: None,
+    }))),
   }))
 }
 
+/// Counts the references of each name, the bindings of the imports left out.
+/// Names are compared without their scope, so a shadowing binding counts as
+/// a reference and keeps the import.
+#[derive(Default)]
+struct ReferenceCounter {
+  references: HashMap<String, usize>,
+}
+
+impl Visit for ReferenceCounter {
+  fn visit_import_decl(&mut self, _: &ImportDecl) {}
+
+  fn visit_ident(&mut self, ident: &Ident) {
+    *self.references.entry(ident.sym.to_string()).or_default() += 1;
+  }
+}
+
+fn count_references(module: &Module) -> HashMap<String, usize> {
+  let mut counter = ReferenceCounter::default();
+  module.visit_with(&mut counter);
+  counter.references
+}
+
+/// Removes the import bindings that were referenced before stripping and are
+/// not anymore. An import left without bindings is removed, or kept as
+/// `import "x"` when `x` matches `side_effects`.
+fn remove_unused_imports(
+  module: &mut Module,
+  before: &HashMap<String, usize>,
+  side_effects: &[ConfigRegex],
+) {
+  let after = count_references(module);
+  let is_unused = |local: &Ident| {
+    before
+      .get(local.sym.as_ref())
+      .is_some_and(|count| *count > 0)
+      && !after.contains_key(local.sym.as_ref())
+  };
+  module.body.retain_mut(|item| {
+    let ModuleItem::ModuleDecl(ModuleDecl::Import(import)) = item else {
+      return true;
+    };
+    if import.specifiers.is_empty() {
+      return true;
+    }
+    import.specifiers.retain(|specifier| {
+      let local = match specifier {
+        ImportSpecifier::Named(named) => &named.local,
+        ImportSpecifier::Default(default) => &default.local,
+        ImportSpecifier::Namespace(namespace) => &namespace.local,
+      };
+      !is_unused(local)
+    });
+    !import.specifiers.is_empty()
+      || side_effects
+        .iter()
+        .any(|regex| regex.is_match(&import.src.value))
+  });
+}
+
 fn flatten(expr: &Expr) -> Option<String> {
   match expr {
     Expr::Member(MemberExpr { obj, prop, .. }) => {
@@ -249,9 +516,11 @@ fn flatten(expr: &Expr) -> Option<String> {
 
       let mut current_obj = obj;
       while let Expr::Member(nested_member) = &**current_obj {
-        if let MemberProp::Ident(ident) = &nested_member.prop {
-          parts.push(ident.sym.to_string());
-        }
+        // `process[key].NODE_ENV` is not `process.NODE_ENV`
+        let MemberProp::Ident(ident) = &nested_member.prop else {
+          return None;
+        };
+        parts.push(ident.sym.to_string());
         current_obj = &nested_member.obj;
       }
 
@@ -271,13 +540,189 @@ fn flatten(expr: &Expr) -> Option<String> {
 fn create_regex_from_list(functions: &[String]) -> Result<Regex, Box<dyn Error>> {
   let patterns: Vec<&str> = functions.iter().map(AsRef::as_ref).collect();
   let joined_patterns = patterns.join("|");
-  let regex = Regex::new(&format!("^(?:{})$", joined_patterns))?;
+  let regex = Regex::new(&format!("^(?:{joined_patterns})$"))?;
   Ok(regex)
 }
 
 fn create_first_pass_regex(first_pass: &[String]) -> Result<Regex, Box<dyn Error>> {
   let patterns: Vec<&str> = first_pass.iter().map(AsRef::as_ref).collect();
   let joined_patterns = patterns.join("|");
-  let regex = Regex::new(&format!(r"\b(?:{})", joined_patterns))?;
+  let regex = Regex::new(&format!(r"\b(?:{joined_patterns})"))?;
   Ok(regex)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_error() {
+    let plugin = FarmPulginStrip::new(
+      &Config::default(),
+      r#"{"functions": ["console\\.log"]}"#.to_string(),
+    );
+    let context = Arc::new(CompilationContext::new(Config::default(), vec![]).unwrap());
+    let err = plugin
+      .transform(
+        &PluginTransformHookParam {
+          module_id: "src/a.ts".to_string(),
+          content: "console.log(1;\n".to_string(),
+          module_type: ModuleType::Ts,
+          resolved_path: "/root/src/a.ts",
+          query: vec![],
+          meta: Default::default(),
+          source_map_chain: vec![],
+        },
+        &context,
+      )
+      .unwrap_err();
+    assert!(
+      matches!(err, CompilationError::ParseError { resolved_path, .. } if resolved_path == "/root/src/a.ts")
+    );
+  }
+
+  fn strip(code: &str, side_effects: &[&str]) -> String {
+    strip_with_defines(code, side_effects, serde_json::json!({}))
+  }
+
+  fn strip_with_defines(code: &str, side_effects: &[&str], defines: serde_json::Value) -> String {
+    let ParseScriptModuleResult { mut ast, .. } = parse_module(
+      &"test.ts".into(),
+      Arc::new(code.to_string()),
+      Syntax::Typescript(TsSyntax::default()),
+      EsVersion::EsNext,
+    )
+    .unwrap();
+    let functions = vec!["console\\.\\w+".to_string(), "assert\\.\\w+".to_string()];
+    let mut remover = StripCode::new(
+      vec!["unittest".to_string()],
+      create_regex_from_list(&functions).unwrap(),
+      true,
+      serde_json::from_value::<HashMap<String, serde_json::Value>>(defines)
+        .unwrap()
+        .iter()
+        .map(|(key, value)| (key.clone(), value_to_expr(value)))
+        .collect(),
+    );
+    let side_effects = side_effects
+      .iter()
+      .map(|s| ConfigRegex::new(s))
+      .collect::<Vec<_>>();
+    strip_module(&mut ast, &mut remover, &side_effects);
+    let (cm, _) = create_swc_source_map(&"test.ts".into(), Arc::new(code.to_string()));
+    let code = codegen_module(&ast, cm, None, Default::default(), None).unwrap();
+    String::from_utf8(code).unwrap()
+  }
+
+  #[test]
+  fn test_strip_statements() {
+    assert_eq!(
+      strip(
+        "console.log(a);\nfunction f() {\n  (assert.ok(a));\n  debugger;\n  unittest: { b(); }\n  return a;\n}\n",
+        &[]
+      ),
+      "function f() {\n    return a;\n}\n"
+    );
+    assert_eq!(
+      strip("if (a) console.log(a);\nelse b();\n", &[]),
+      "if (a) {} else b();\n"
+    );
+    assert_eq!(
+      strip("const b = a || console.log(a);\n", &[]),
+      "const b = a || void 0;\n"
+    );
+  }
+
+  #[test]
+  fn test_remove_unused_imports() {
+    assert_eq!(
+      strip(
+        "import logger, { format } from './logger';\nimport { a } from './a';\nconsole.log(logger(a));\nformat(a);\n",
+        &[]
+      ),
+      "import { format } from './logger';\nimport { a } from './a';\nformat(a);\n"
+    );
+    assert_eq!(
+      strip(
+        "import * as debug from 'debug';\nimport trace from './trace';\nimport './polyfill';\nimport unused from 'unused';\nassert.ok(debug.enabled, trace);\n",
+        &["^\\./trace$"]
+      ),
+      "import './trace';\nimport './polyfill';\nimport unused from 'unused';\n"
+    );
+    // names are compared without their scope, the parameter keeps the import
+    assert_eq!(
+      strip(
+        "import { log } from './log';\nconsole.log(log);\nfunction f(log) {\n  return log;\n}\n",
+        &[]
+      ),
+      "import { log } from './log';\nfunction f(log) {\n    return log;\n}\n"
+    );
+  }
+
+  #[test]
+  fn test_defines() {
+    let defines = serde_json::json!({ "__DEV__": false, "process.env.NODE_ENV": "production" });
+    let strip = |code: &str| strip_with_defines(code, &[], defines.clone());
+    assert_eq!(
+      strip(
+        "'use strict';\nimport { check } from './check';\nif (__DEV__) {\n  check();\n  var warned = true;\n} else {\n  run();\n}\n__DEV__ && check();\nconst env = process.env.NODE_ENV !== 'production' ? 'dev' : 'prod';\n"
+      ),
+      "'use strict';\nvar warned;\nrun();\nconst env = 'prod';\n"
+    );
+    assert_eq!(
+      strip(
+        "function f(a) {\n  if (a) {\n    if (!__DEV__) return { __DEV__, mode: typeof __DEV__ };\n    else console.log(a);\n  }\n  return __DEV__ || a;\n}\n"
+      ),
+      "function f(a) {\n    if (a) {\n        return {\n            __DEV__: false,\n            mode: \"boolean\"\n        };\n    }\n    return a;\n}\n"
+    );
+    assert_eq!(
+      strip("if (!__DEV__) {\n  const a = 1;\n  run(a);\n}\n"),
+      "{\n    const a = 1;\n    run(a);\n}"
+    );
+    let config = serde_json::json!({ "CONFIG": { "a": { "b": 1 } } });
+    assert_eq!(
+      strip_with_defines(
+        "const get = () => CONFIG;\nCONFIG.a;\n",
+        &[],
+        config
+      ),
+      "const get = ()=>({\n        \"a\": {\n            \"b\": 1\n        }\n    });\n({\n    \"a\": {\n        \"b\": 1\n    }\n}).a;\n"
+    );
+    let neg = serde_json::json!({ "NEG": -1 });
+    assert_eq!(
+      strip_with_defines("NEG ** 2;\nNEG.toFixed(1);\n", &[], neg),
+      "(-1) ** 2;\n(-1).toFixed(1);\n"
+    );
+    assert_eq!(
+      strip("process.env[x].NODE_ENV;\nprocess[x].env.NODE_ENV;\n"),
+      "process.env[x].NODE_ENV;\nprocess[x].env.NODE_ENV;\n"
+    );
+    // a local binding shadows the define, an ambient declaration does not
+    assert_eq!(
+      strip("const __DEV__ = true;\nif (__DEV__) run();\n"),
+      "const __DEV__ = true;\nif (__DEV__) run();\n"
+    );
+    assert_eq!(
+      strip("declare const __DEV__: boolean;\nif (__DEV__) {\n  const a = 1;\n  run(a);\n}\n"),
+      "declare const __DEV__: boolean;\n"
+    );
+    // sloppy-mode scripts hoist the functions of blocks, modules do not
+    assert_eq!(
+      strip("if (__DEV__) {\n  function warn() {}\n}\nwarn;\n"),
+      "var warn;\nwarn;\n"
+    );
+    assert_eq!(
+      strip("if (__DEV__) {\n  function warn() {}\n}\nexport { run };\n"),
+      "export { run };\n"
+    );
+  }
+
+  #[test]
+  fn test_no_defines() {
+    let code = "if (0) {\n  run();\n}\nconst same = 'a' === 'a';\n";
+    assert_eq!(
+      strip_with_defines(code, &[], serde_json::json!({})),
+      "if (0) {\n    run();\n}\nconst same = 'a' === 'a';\n"
+    );
+  }
+}
@@ -1,3 +1,59 @@
 export interface IPluginOptions {
-  
+  /**
+   * How documents separated by `---` are imported.
+   * - `single`: the file must hold one document, which is the default export.
+   *   A file holding several documents fails the build.
+   * - `multi`: the default export is an array with one entry per document.
+   *
+   * @default 'single'
+   */
+  documentMode?: 'single' | 'multi';
+
+  /**
+   * Specifies an array of regex patterns to include files in the plugin process.
+   * Each string in the array should be a valid regular expression used to match file paths.
+   *
+   * @example
+   *  ["src/config/.*\\.ya?ml$"]
+   */
+  include?: string[];
+
+  /**
+   * Specifies an array of regex patterns to exclude files from the plugin process.
+   * Each string in the array should be a valid regular expression used to match file paths.
+   *
+   * @example
+   *  [".*\\.local\\.yaml$"]
+   */
+  exclude?: string[];
+
+  /**
+   * Writes a `<name>.d.<ext>.ts` declaration next to every YAML file, typed from the shape
+   * of its content, e.g. `config.d.yaml.ts` for `config.yaml`.
+   * This requires `allowArbitraryExtensions` in `tsconfig.json`.
+   *
+   * @default false
+   */
+  dts?: boolean;
+
+  /**
+   * Replaces `${ENV_VAR}` and `${ENV_VAR:-default}` placeholders in string values with
+   * environment variables. Unset variables without a default are replaced with an empty string.
+   * A value made of a single placeholder is typed like a plain YAML scalar, e.g. `port: ${PORT:-3000}`
+   * is a number.
+   *
+   * @default false
+   */
+  interpolateEnv?: boolean;
+
+  /**
+   * Validates the YAML files matching `files`, a glob relative to the project root, against
+   * the JSON Schema at `schema`, relative to the project root. Violations fail the build with the
+   * path of the invalid value and the failing keyword. In `multi` document mode every document
+   * is validated.
+   *
+   * @example
+   *  [{ files: 'config/**\/*.yaml', schema: 'schemas/config.schema.json' }]
+   */
+  schemas?: { files: string; schema: string }[];
 }
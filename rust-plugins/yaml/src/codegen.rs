@@ -0,0 +1,87 @@
+use farmfe_core::{
+  serde_json::{self, Value},
+  swc_ecma_ast::Ident,
+};
+
+/// 检查 key 是否可以直接作为 `export var {key}` 的变量名，保留字由 swc 判断
+pub fn is_valid_identifier(name: &str) -> bool {
+  Ident::verify_symbol(name).is_ok()
+}
+
+/// 顶层 key 的具名导出，`local` 与 `name` 不同时通过
+/// `export { local as "name" }` 导出
+pub struct NamedExport<'a> {
+  pub name: &'a str,
+  pub local: String,
+  pub value: &'a Value,
+}
+
+/// 顶层对象每个 key 一个具名导出，`default` 会与默认导出冲突，因此跳过
+pub fn named_exports(value: &Value) -> Vec<NamedExport<'_>> {
+  let Value::Object(object) = value else {
+    return vec![];
+  };
+  object
+    .iter()
+    .enumerate()
+    .filter(|(_, (key, _))| key.as_str() != "default")
+    .map(|(index, (key, value))| NamedExport {
+      name: key,
+      local: if is_valid_identifier(key) {
+        key.clone()
+      } else {
+        format!("__yaml_export_{index}")
+      },
+      value,
+    })
+    .collect()
+}
+
+/// 生成默认导出以及顶层 key 的具名导出
+pub fn stringify_module(value: &Value) -> String {
+  let mut code = format!("export default {value};\n\n");
+  for export in named_exports(value) {
+    if export.local == export.name {
+      code.push_str(&format!(
+        "export var {} = {};\n",
+        export.local, export.value
+      ));
+    } else {
+      code.push_str(&format!(
+        "var {} = {};\nexport {{ {} as {} }};\n",
+        export.local,
+        export.value,
+        export.local,
+        serde_json::to_string(export.name).unwrap()
+      ));
+    }
+  }
+  code
+}
+
+/// 默认导出一个字符串，用于 `?raw` 与 `?json`
+pub fn stringify_string_module(content: &str) -> String {
+  format!(
+    "export default {};\n",
+    serde_json::to_string(content).unwrap()
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_unsafe_keys() {
+    let value = serde_json::json!({ "name": "farm", "my-key": 1, "class": true, "default": 0 });
+    assert_eq!(
+      stringify_module(&value),
+      "export default {\"name\":\"farm\",\"my-key\":1,\"class\":true,\"default\":0};\n\n\
+       export var name = \"farm\";\n\
+       var __yaml_export_1 = 1;\nexport { __yaml_export_1 as \"my-key\" };\n\
+       var __yaml_export_2 = true;\nexport { __yaml_export_2 as \"class\" };\n"
+    );
+    assert!(!is_valid_identifier("yield"));
+    assert!(!is_valid_identifier("protected"));
+  }
+}
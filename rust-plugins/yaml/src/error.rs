@@ -0,0 +1,121 @@
+use farmfe_core::error::CompilationError;
+
+/// 代码帧中错误行之前展示的行数，YAML 的缩进依赖上文
+const CONTEXT_LINES: usize = 2;
+
+/// 将 serde_yaml 的错误转换为带行列号与代码帧的 [`CompilationError::ParseError`]
+pub fn yaml_error_to_compilation_error(
+  resolved_path: &str,
+  content: &str,
+  err: &serde_yaml::Error,
+) -> CompilationError {
+  let reason = err.to_string();
+  match err.location() {
+    Some(location) => {
+      // serde_yaml 的错误信息末尾带有位置，这里统一放到文件路径之后
+      let reason = reason
+        .rsplit_once(" at line ")
+        .map_or(reason.as_str(), |(reason, _)| reason);
+      parse_error(
+        resolved_path,
+        content,
+        location.line(),
+        location.column(),
+        reason,
+      )
+    }
+    None => CompilationError::ParseError {
+      resolved_path: resolved_path.to_string(),
+      msg: format!("Failed to parse {resolved_path}: {reason}"),
+    },
+  }
+}
+
+/// 指向 `content` 第 `line` 行第 `column` 列（均从 1 开始）的解析错误
+pub fn parse_error(
+  resolved_path: &str,
+  content: &str,
+  line: usize,
+  column: usize,
+  reason: &str,
+) -> CompilationError {
+  CompilationError::ParseError {
+    resolved_path: resolved_path.to_string(),
+    msg: format!(
+      "Failed to parse {resolved_path}:{line}:{column}: {reason}\n\n{}",
+      code_frame(content, line, column)
+    ),
+  }
+}
+
+fn code_frame(content: &str, line: usize, column: usize) -> String {
+  let lines = content.lines().collect::<Vec<_>>();
+  // 错误位于文件末尾时，libyaml 可能指向最后一行之后
+  let line = line.clamp(1, lines.len().max(1));
+  let first = line.saturating_sub(CONTEXT_LINES).max(1);
+  let gutter = line.to_string().len();
+  let mut frame = (first..=line)
+    .filter_map(|number| {
+      lines
+        .get(number - 1)
+        .map(|source| format!("{number:>gutter$} | {source}\n"))
+    })
+    .collect::<String>();
+  frame.push_str(&format!(
+    "{} | {}^",
+    " ".repeat(gutter),
+    " ".repeat(column.saturating_sub(1))
+  ));
+  frame
+}
+
+/// 第 `index` 个（从 0 开始）文档开始的行号，从 1 开始
+pub fn document_start_line(content: &str, index: usize) -> usize {
+  let mut document = 0;
+  let mut has_content = false;
+  for (number, source) in content.lines().enumerate() {
+    if source == "---" || source.starts_with("--- ") {
+      // 文件开头的 `---` 不分隔文档
+      if has_content {
+        document += 1;
+      }
+      has_content = true;
+      if document == index {
+        return number + 1;
+      }
+    } else if !source.trim().is_empty() && !source.trim_start().starts_with('#') {
+      has_content = true;
+    }
+  }
+  1
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_yaml_error() {
+    let content = "name: farm\nserver:\n  port: 9000\n host: localhost\n";
+    let err = serde_yaml::from_str::<serde_yaml::Value>(content).unwrap_err();
+    let CompilationError::ParseError { msg, .. } =
+      yaml_error_to_compilation_error("config.yaml", content, &err)
+    else {
+      panic!("expected a parse error");
+    };
+    assert_eq!(
+      msg,
+      "Failed to parse config.yaml:4:2: did not find expected key\n\n\
+       2 | server:\n\
+       3 |   port: 9000\n\
+       4 |  host: localhost\n  \
+         |  ^"
+    );
+  }
+
+  #[test]
+  fn test_document_start_line() {
+    assert_eq!(document_start_line("a: 1\n---\nb: 2\n", 1), 2);
+    assert_eq!(document_start_line("# head\n---\na: 1\n---\nb: 2\n", 1), 4);
+  }
+}
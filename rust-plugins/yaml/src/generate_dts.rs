@@ -0,0 +1,124 @@
+use std::{
+  fs, io,
+  path::{Path, PathBuf},
+};
+
+use farmfe_core::serde_json::{self, Value};
+
+use crate::codegen::{is_valid_identifier, named_exports};
+
+const HEADER: &str = "/* generated by farmfe_plugin_yaml */\n";
+
+/// 根据 YAML 的值推导 TypeScript 类型，数组元素类型取并集
+pub fn stringify_type(value: &Value, depth: usize) -> String {
+  match value {
+    Value::Null => "null".to_string(),
+    Value::Bool(_) => "boolean".to_string(),
+    Value::Number(_) => "number".to_string(),
+    Value::String(_) => "string".to_string(),
+    Value::Array(items) => {
+      let mut types = items
+        .iter()
+        .map(|item| stringify_type(item, depth))
+        .collect::<Vec<_>>();
+      types.sort();
+      types.dedup();
+      match types.len() {
+        0 => "unknown[]".to_string(),
+        1 => format!("Array<{}>", types[0]),
+        _ => format!("Array<{}>", types.join(" | ")),
+      }
+    }
+    Value::Object(object) => {
+      if object.is_empty() {
+        return "{}".to_string();
+      }
+      let indent = "  ".repeat(depth + 1);
+      let fields = object
+        .iter()
+        .map(|(key, value)| {
+          let key = if is_valid_identifier(key) {
+            key.clone()
+          } else {
+            serde_json::to_string(key).unwrap()
+          };
+          format!("{indent}{key}: {};\n", stringify_type(value, depth + 1))
+        })
+        .collect::<String>();
+      format!("{{\n{fields}{}}}", "  ".repeat(depth))
+    }
+  }
+}
+
+/// 多文档模式下默认导出为元组，每个文档一项
+pub fn stringify_documents_type(documents: &[Value]) -> String {
+  let types = documents
+    .iter()
+    .map(|document| stringify_type(document, 0))
+    .collect::<Vec<_>>();
+  format!("[{}]", types.join(", "))
+}
+
+/// 生成模块声明：默认导出以及与 `codegen::stringify_module` 对应的具名导出
+pub fn stringify_module_dts(default_type: &str, value: &Value) -> String {
+  let mut code = format!("{HEADER}declare const data: {default_type};\nexport default data;\n");
+  for export in named_exports(value) {
+    let ty = stringify_type(export.value, 0);
+    if export.local == export.name {
+      code.push_str(&format!("export declare const {}: {ty};\n", export.local));
+    } else {
+      code.push_str(&format!(
+        "declare const {}: {ty};\nexport {{ {} as {} }};\n",
+        export.local,
+        export.local,
+        serde_json::to_string(export.name).unwrap()
+      ));
+    }
+  }
+  code
+}
+
+/// `config.yaml` -> `config.d.yaml.ts`，开启 `allowArbitraryExtensions` 后
+/// TypeScript 会读取该声明文件
+pub fn dts_path(resolved_path: &str) -> Option<PathBuf> {
+  let path = Path::new(resolved_path);
+  let stem = path.file_stem()?.to_str()?;
+  let ext = path.extension()?.to_str()?;
+  Some(path.with_file_name(format!("{stem}.d.{ext}.ts")))
+}
+
+/// 内容未变化时不写入，避免触发编辑器与类型检查的监听
+pub fn write_dts(path: &Path, content: &str) -> io::Result<()> {
+  if fs::read_to_string(path).is_ok_and(|existing| existing == content) {
+    return Ok(());
+  }
+  fs::write(path, content)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_stringify_module_dts() {
+    let value = serde_json::json!({
+      "name": "farm",
+      "my-key": [1, "a", 2],
+      "server": { "port": 9000, "hosts": [] }
+    });
+    assert_eq!(
+      stringify_module_dts(&stringify_type(&value, 0), &value),
+      "/* generated by farmfe_plugin_yaml */\n\
+       declare const data: {\n  name: string;\n  \"my-key\": Array<number | string>;\n  server: {\n    port: number;\n    hosts: unknown[];\n  };\n};\n\
+       export default data;\n\
+       export declare const name: string;\n\
+       declare const __yaml_export_1: Array<number | string>;\n\
+       export { __yaml_export_1 as \"my-key\" };\n\
+       export declare const server: {\n  port: number;\n  hosts: unknown[];\n};\n"
+    );
+    assert_eq!(
+      stringify_documents_type(&[serde_json::json!(1), serde_json::json!(null)]),
+      "[number, null]"
+    );
+  }
+}
@@ -1,155 +1,459 @@
 #![deny(clippy::all)]
+mod codegen;
+mod error;
+mod generate_dts;
+mod resolve;
+mod schema;
 
 use farmfe_core::{
-    config::Config,
-    module::ModuleType,
-    plugin::{Plugin, PluginLoadHookParam, PluginLoadHookResult, PluginTransformHookParam, PluginTransformHookResult},
-    serde_json
+  config::{config_regex::ConfigRegex, Config},
+  error::CompilationError,
+  module::{ModuleId, ModuleType},
+  plugin::{
+    Plugin, PluginLoadHookParam, PluginLoadHookResult, PluginTransformHookParam,
+    PluginTransformHookResult,
+  },
+  serde_json,
 };
 use farmfe_macro_plugin::farm_plugin;
+use farmfe_toolkit::plugin_utils::path_filter::PathFilter;
 use lazy_static::lazy_static;
-use regex::Regex;
 use serde::Deserialize;
 use std::fs::read_to_string;
+use std::path::Path;
 
 lazy_static! {
-    static ref YAML_MODULE_TYPE: String = String::from("yaml");
+  static ref YAML_MODULE_TYPE: String = String::from("yaml");
 }
 
 /// 检查文件是否为 YAML 文件
-fn is_yaml_file(file_name: &String) -> bool {
-    file_name.ends_with(".yaml") || file_name.ends_with(".yml")
+fn is_yaml_file(file_name: &str) -> bool {
+  file_name.ends_with(".yaml") || file_name.ends_with(".yml")
 }
 
 /// YAML 文档解析模式
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 enum DocumentMode {
-    /// 单文档模式
-    Single,
-    /// 多文档模式
-    Multi
+  /// 单文档模式
+  Single,
+  /// 多文档模式
+  Multi,
+}
+
+/// 导入时通过查询参数选择的导出形式
+#[derive(Debug, PartialEq)]
+enum QueryMode {
+  /// 默认导出解析后的数据
+  Data,
+  /// `?raw`：默认导出 YAML 原文
+  Raw,
+  /// `?json`：默认导出解析后数据的 JSON 字符串
+  Json,
+}
+
+impl QueryMode {
+  fn from_query(query: &[(String, String)]) -> Self {
+    if query.iter().any(|(k, _)| k == "raw") {
+      QueryMode::Raw
+    } else if query.iter().any(|(k, _)| k == "json") {
+      QueryMode::Json
+    } else {
+      QueryMode::Data
+    }
+  }
 }
 
 /// YAML 插件配置选项
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FarmPluginYamlOptions {
-    document_mode: Option<DocumentMode>,
-    include: Option<String>,
-    exclude: Option<String>,
+  document_mode: Option<DocumentMode>,
+  include: Option<Vec<ConfigRegex>>,
+  exclude: Option<Vec<ConfigRegex>>,
+  /// 在每个 YAML 文件旁生成 `<name>.d.<ext>.ts` 类型声明
+  dts: Option<bool>,
+  /// 替换字符串中的 `${ENV_VAR:-default}` 环境变量占位符
+  interpolate_env: Option<bool>,
+  /// 使用 JSON Schema 校验匹配 glob 的 YAML 文件
+  schemas: Option<Vec<schema::SchemaOption>>,
 }
 
 /// Farm YAML 插件
 #[farm_plugin]
 pub struct FarmPluginYaml {
-    document_mode: DocumentMode,
-    include: String,
-    exclude: String,
+  document_mode: DocumentMode,
+  include: Vec<ConfigRegex>,
+  exclude: Vec<ConfigRegex>,
+  dts: bool,
+  interpolate_env: bool,
+  /// `(schema 路径, 编译后的 schema)`，schema 无效时为错误信息，在构建开始时报告
+  schemas: Result<Vec<(String, schema::Schema)>, String>,
+  root: String,
 }
 
 impl FarmPluginYaml {
-    fn new(_config: &Config, options: String) -> Self {
-        let yaml_options: FarmPluginYamlOptions = serde_json::from_str(&options)
-            .expect("Failed to parse YAML plugin options");
-        let include: String = yaml_options.include.unwrap_or_default();
-        let exclude: String = yaml_options.exclude.unwrap_or_default();
-        Self {
-            document_mode: yaml_options.document_mode.unwrap_or(DocumentMode::Single),
-            include,
-            exclude,
-        }
+  fn new(config: &Config, options: String) -> Self {
+    let yaml_options: FarmPluginYamlOptions =
+      serde_json::from_str(&options).expect("Failed to parse YAML plugin options");
+    let include = yaml_options.include.unwrap_or_default();
+    let exclude = yaml_options.exclude.unwrap_or_default();
+    Self {
+      document_mode: yaml_options.document_mode.unwrap_or(DocumentMode::Single),
+      include,
+      exclude,
+      dts: yaml_options.dts.unwrap_or(false),
+      interpolate_env: yaml_options.interpolate_env.unwrap_or(false),
+      schemas: yaml_options
+        .schemas
+        .unwrap_or_default()
+        .iter()
+        .map(|option| {
+          schema::Schema::new(option, &config.root).map(|schema| (option.schema.clone(), schema))
+        })
+        .collect(),
+      root: config.root.clone(),
     }
+  }
 
-    /// 检查是否应处理指定路径
-    fn should_process_path(&self, path: &str) -> bool {
-        if !self.include.is_empty() {
-            let inc_reg = match Regex::new(&self.include) {
-                Ok(reg) => reg,
-                Err(_) => return false,
-            };
-            if inc_reg.find(path).is_none() {
-                return false;
-            }
-        }
+  /// 使用匹配文件路径的 schema 校验解析结果，多文档模式下逐个文档校验
+  fn validate(
+    &self,
+    resolved_path: &str,
+    value: &serde_json::Value,
+  ) -> Result<(), CompilationError> {
+    let relative_path = Path::new(resolved_path)
+      .strip_prefix(&self.root)
+      .unwrap_or(Path::new(resolved_path))
+      .to_string_lossy()
+      .replace('\\', "/");
+    for (schema_path, schema) in self.schemas()? {
+      if !schema.matches(&relative_path) {
+        continue;
+      }
+      let violations = match (&self.document_mode, value) {
+        (DocumentMode::Multi, serde_json::Value::Array(documents)) => documents
+          .iter()
+          .enumerate()
+          .flat_map(|(index, document)| schema.validate(document, Some(index)))
+          .collect(),
+        _ => schema.validate(value, None),
+      };
+      if !violations.is_empty() {
+        return Err(schema::validation_error(
+          resolved_path,
+          schema_path,
+          &violations,
+        ));
+      }
+    }
+    Ok(())
+  }
 
-        if !self.exclude.is_empty() {
-            let exc_reg = match Regex::new(&self.exclude) {
-                Ok(reg) => reg,
-                Err(_) => return true,
-            };
-            if exc_reg.find(path).is_some() {
-                return false;
-            }
-        }
+  /// 编译后的 schema，无效的 schema 作为插件错误返回
+  fn schemas(&self) -> Result<&[(String, schema::Schema)], CompilationError> {
+    self
+      .schemas
+      .as_deref()
+      .map_err(|e| CompilationError::GenericError(e.clone()))
+  }
 
-        true
-    }
+  /// 检查是否应处理指定路径，正则只在创建插件时编译一次
+  fn should_process_path(&self, path: &str) -> bool {
+    is_yaml_file(path) && PathFilter::new(&self.include, &self.exclude).execute(path)
+  }
 
-    /// 将 YAML 内容转换为 JavaScript 导出
-    fn yaml_to_js(&self, content: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let result = match self.document_mode {
-            DocumentMode::Single | DocumentMode::Multi => {
-                serde_yaml::from_str::<serde_json::Value>(content)?
-            }
-        };
-
-        let mut export_val = String::new();
-        if let serde_json::Value::Object(object) = result.clone() {
-            for (key, val) in object {
-                export_val.push_str(&format!("export var {} = {};\n", key, val));
-            }
+  /// 按文档模式解析 YAML 内容，通过 `!include` 引入的文件记录在 `resolver` 中，
+  /// 解析失败时也保留已引入的文件
+  fn parse_yaml(
+    &self,
+    resolver: &mut resolve::Resolver,
+    resolved_path: &str,
+    content: &str,
+  ) -> Result<serde_json::Value, CompilationError> {
+    let documents = resolver
+      .resolve_documents(resolved_path, content)?
+      .into_iter()
+      .map(serde_json::to_value)
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(|e| CompilationError::ParseError {
+        resolved_path: resolved_path.to_string(),
+        msg: format!("Failed to parse {resolved_path}: {e}"),
+      })?;
+    let value = match self.document_mode {
+      DocumentMode::Single => match documents.len() {
+        0 => serde_json::Value::Null,
+        1 => documents.into_iter().next().unwrap(),
+        count => {
+          return Err(error::parse_error(
+            resolved_path,
+            content,
+            error::document_start_line(content, 1),
+            1,
+            &format!(
+              "found {count} documents separated by `---`, \
+               set `documentMode: 'multi'` to import every document"
+            ),
+          ))
         }
+      },
+      // 多文档模式导出数组，每个文档一项
+      DocumentMode::Multi => serde_json::Value::Array(documents),
+    };
+    Ok(value)
+  }
 
-        Ok(format!("export default {};\n\n{}", result, export_val))
-    }
+  /// 根据 YAML 的结构生成类型声明
+  fn write_dts(&self, resolved_path: &str, value: &serde_json::Value) -> std::io::Result<()> {
+    let Some(dts_path) = generate_dts::dts_path(resolved_path) else {
+      return Ok(());
+    };
+    let default_type = match (&self.document_mode, value) {
+      (DocumentMode::Multi, serde_json::Value::Array(documents)) => {
+        generate_dts::stringify_documents_type(documents)
+      }
+      _ => generate_dts::stringify_type(value, 0),
+    };
+    generate_dts::write_dts(
+      &dts_path,
+      &generate_dts::stringify_module_dts(&default_type, value),
+    )
+  }
 }
 
 impl Plugin for FarmPluginYaml {
-    fn name(&self) -> &str {
-        "FarmPluginYaml"
+  fn name(&self) -> &str {
+    "FarmPluginYaml"
+  }
+
+  fn build_start(
+    &self,
+    _context: &std::sync::Arc<farmfe_core::context::CompilationContext>,
+  ) -> Result<Option<()>, CompilationError> {
+    self.schemas()?;
+    Ok(None)
+  }
+
+  fn load(
+    &self,
+    param: &PluginLoadHookParam,
+    _context: &std::sync::Arc<farmfe_core::context::CompilationContext>,
+    _hook_context: &farmfe_core::plugin::PluginHookContext,
+  ) -> farmfe_core::error::Result<Option<PluginLoadHookResult>> {
+    // module_id 可能带有 `?json` 等查询参数，因此按 resolved_path 判断
+    if self.should_process_path(param.resolved_path) {
+      let content =
+        read_to_string(param.resolved_path).map_err(|e| CompilationError::LoadError {
+          resolved_path: param.resolved_path.to_string(),
+          source: Some(Box::new(e)),
+        })?;
+      return Ok(Some(PluginLoadHookResult {
+        content,
+        source_map: None,
+        module_type: ModuleType::Custom(YAML_MODULE_TYPE.to_string()),
+      }));
     }
+    Ok(None)
+  }
 
-    fn load(
-        &self,
-        param: &PluginLoadHookParam,
-        _context: &std::sync::Arc<farmfe_core::context::CompilationContext>,
-        _hook_context: &farmfe_core::plugin::PluginHookContext,
-    ) -> farmfe_core::error::Result<Option<PluginLoadHookResult>> {
-        if is_yaml_file(&param.module_id) {
-            let content = read_to_string(param.resolved_path).unwrap();
-            return Ok(Some(PluginLoadHookResult {
-                content,
-                source_map: None,
-                module_type: ModuleType::Custom(YAML_MODULE_TYPE.to_string()),
-            }));
-        }
-        Ok(None)
+  fn transform(
+    &self,
+    param: &PluginTransformHookParam,
+    context: &std::sync::Arc<farmfe_core::context::CompilationContext>,
+  ) -> farmfe_core::error::Result<Option<PluginTransformHookResult>> {
+    if !self.should_process_path(param.resolved_path) {
+      return Ok(None);
     }
 
-    fn transform(
-        &self,
-        param: &PluginTransformHookParam,
-        _context: &std::sync::Arc<farmfe_core::context::CompilationContext>,
-    ) -> farmfe_core::error::Result<Option<PluginTransformHookResult>> {
-        if param.module_type != ModuleType::Custom(YAML_MODULE_TYPE.to_string()) {
-            return Ok(None);
-        }
+    let query_mode = QueryMode::from_query(&param.query);
+    // `?raw` 由 Farm 内置插件加载为 Asset，内容为空
+    if query_mode == QueryMode::Raw {
+      let content = if param.content.is_empty() {
+        read_to_string(param.resolved_path).map_err(|e| CompilationError::LoadError {
+          resolved_path: param.resolved_path.to_string(),
+          source: Some(Box::new(e)),
+        })?
+      } else {
+        param.content.clone()
+      };
+      return Ok(Some(PluginTransformHookResult {
+        content: codegen::stringify_string_module(&content.replace("\r\n", "\n")),
+        module_type: Some(ModuleType::Js),
+        source_map: None,
+        ignore_previous_source_map: false,
+      }));
+    }
+
+    if param.module_type != ModuleType::Custom(YAML_MODULE_TYPE.to_string()) {
+      return Ok(None);
+    }
 
-        if !self.should_process_path(param.resolved_path) {
-            return Ok(None);
+    let mut resolver = resolve::Resolver::new(param.resolved_path, self.interpolate_env);
+    let value = self.parse_yaml(&mut resolver, param.resolved_path, &param.content);
+    if !resolver.included.is_empty() {
+      // 被引入的文件变化时重新编译当前模块，引入的文件有误时修复后同样会重新编译
+      context.add_watch_files(
+        param.module_id.as_str().into(),
+        resolver
+          .included
+          .iter()
+          .map(|path| ModuleId::new(&path.to_string_lossy(), "", &context.config.root))
+          .collect(),
+      )?;
+    }
+    let value = value?;
+    self.validate(param.resolved_path, &value)?;
+
+    if query_mode == QueryMode::Json {
+      return Ok(Some(PluginTransformHookResult {
+        content: codegen::stringify_string_module(&value.to_string()),
+        module_type: Some(ModuleType::Js),
+        source_map: None,
+        ignore_previous_source_map: false,
+      }));
+    }
+
+    if self.dts {
+      self.write_dts(param.resolved_path, &value).map_err(|e| {
+        CompilationError::TransformError {
+          resolved_path: param.resolved_path.to_string(),
+          msg: format!("Failed to write the type declarations: {e}"),
         }
+      })?;
+    }
+    let code = codegen::stringify_module(&value);
 
-        let code = match self.yaml_to_js(&param.content) {
-            Ok(code) => code,
-            Err(e) => panic!("Failed to parse YAML: {}", e),
-        };
-
-        Ok(Some(PluginTransformHookResult {
-            content: code,
-            module_type: Some(ModuleType::Js),
-            source_map: None,
-            ignore_previous_source_map: false,
-        }))
+    Ok(Some(PluginTransformHookResult {
+      content: code,
+      module_type: Some(ModuleType::Js),
+      source_map: None,
+      ignore_previous_source_map: false,
+    }))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  impl FarmPluginYaml {
+    fn yaml_to_js(&self, content: &str) -> Result<String, CompilationError> {
+      let mut resolver = resolve::Resolver::new("test.yaml", self.interpolate_env);
+      Ok(codegen::stringify_module(&self.parse_yaml(
+        &mut resolver,
+        "test.yaml",
+        content,
+      )?))
     }
+  }
+
+  fn plugin(document_mode: DocumentMode) -> FarmPluginYaml {
+    FarmPluginYaml {
+      document_mode,
+      include: vec![],
+      exclude: vec![],
+      dts: false,
+      interpolate_env: false,
+      schemas: Ok(vec![]),
+      root: String::new(),
+    }
+  }
+
+  #[test]
+  fn test_single_document_mode() {
+    let code = plugin(DocumentMode::Single)
+      .yaml_to_js("name: farm\nversion: 2\n")
+      .unwrap();
+    assert_eq!(
+      code,
+      "export default {\"name\":\"farm\",\"version\":2};\n\n\
+       export var name = \"farm\";\nexport var version = 2;\n"
+    );
+
+    let err = plugin(DocumentMode::Single)
+      .parse_yaml(
+        &mut resolve::Resolver::new("test.yaml", false),
+        "test.yaml",
+        "name: farm\n---\nname: vite\n",
+      )
+      .unwrap_err();
+    assert!(matches!(
+      err,
+      CompilationError::ParseError { msg, .. }
+        if msg.starts_with("Failed to parse test.yaml:2:1: found 2 documents")
+    ));
+  }
+
+  #[test]
+  fn test_multi_document_mode() {
+    let code = plugin(DocumentMode::Multi)
+      .yaml_to_js("name: farm\n---\n- 1\n- 2\n")
+      .unwrap();
+    assert_eq!(code, "export default [{\"name\":\"farm\"},[1,2]];\n\n");
+  }
+
+  #[test]
+  fn test_path_filter_and_query_mode() {
+    let mut yaml = plugin(DocumentMode::Single);
+    yaml.include = vec![ConfigRegex::new("config/")];
+    yaml.exclude = vec![ConfigRegex::new(r"\.local\.yaml$")];
+    assert!(yaml.should_process_path("/app/config/app.yaml"));
+    assert!(!yaml.should_process_path("/app/config/app.local.yaml"));
+    assert!(!yaml.should_process_path("/app/data/app.yaml"));
+    assert!(!yaml.should_process_path("/app/config/app.json"));
+
+    let query = |key: &str| vec![(key.to_string(), String::new())];
+    assert_eq!(QueryMode::from_query(&query("raw")), QueryMode::Raw);
+    assert_eq!(QueryMode::from_query(&query("json")), QueryMode::Json);
+    assert_eq!(QueryMode::from_query(&[]), QueryMode::Data);
+  }
+
+  #[test]
+  fn test_watch_broken_includes() {
+    let dir = std::env::temp_dir().join(format!("farmfe_plugin_yaml_watch_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("base.yaml"), "port: 9000\n").unwrap();
+    std::fs::write(dir.join("broken.yaml"), "port: [9000\n").unwrap();
+    let entry = dir.join("app.yaml").to_string_lossy().to_string();
+    let context = std::sync::Arc::new(
+      farmfe_core::context::CompilationContext::new(Config::default(), vec![]).unwrap(),
+    );
+    let result = plugin(DocumentMode::Single).transform(
+      &PluginTransformHookParam {
+        module_id: "app.yaml".to_string(),
+        content: "base: !include base.yaml\nbroken: !include broken.yaml\n".to_string(),
+        module_type: ModuleType::Custom(YAML_MODULE_TYPE.to_string()),
+        resolved_path: &entry,
+        query: vec![],
+        meta: Default::default(),
+        source_map_chain: vec![],
+      },
+      &context,
+    );
+    assert!(result.is_err());
+    let watch_graph = context.watch_graph.read();
+    let watched = watch_graph.dependencies(&"app.yaml".into());
+    assert_eq!(watched.len(), 2);
+    assert!(watched
+      .iter()
+      .any(|module_id| module_id.to_string().ends_with("broken.yaml")));
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn test_invalid_schema() {
+    let yaml = FarmPluginYaml::new(
+      &Config::default(),
+      r#"{"schemas": [{"files": "*.yaml", "schema": "missing.schema.json"}]}"#.to_string(),
+    );
+    let context = std::sync::Arc::new(
+      farmfe_core::context::CompilationContext::new(Config::default(), vec![]).unwrap(),
+    );
+    let err = yaml.build_start(&context).unwrap_err();
+    assert!(matches!(
+      err,
+      CompilationError::GenericError(msg)
+        if msg.starts_with("Failed to read the JSON Schema")
+    ));
+  }
 }
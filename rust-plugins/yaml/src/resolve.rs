@@ -0,0 +1,260 @@
+use std::{
+  fs::{canonicalize, read_to_string},
+  path::{Path, PathBuf},
+};
+
+use farmfe_core::error::CompilationError;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_yaml::Value;
+
+use crate::error::yaml_error_to_compilation_error;
+
+lazy_static! {
+  static ref ENV_PLACEHOLDER: Regex =
+    Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").unwrap();
+}
+
+/// 展开 YAML 文档：解析 `!include` 标签、`<<` 合并键以及可选的环境变量插值
+pub struct Resolver {
+  interpolate_env: bool,
+  /// 被引入的所有文件，用于注册监听
+  pub included: Vec<PathBuf>,
+  /// 正在展开的文件链，用于检测循环引入
+  stack: Vec<PathBuf>,
+}
+
+impl Resolver {
+  pub fn new(resolved_path: &str, interpolate_env: bool) -> Self {
+    Self {
+      interpolate_env,
+      included: vec![],
+      stack: vec![canonicalize(resolved_path).unwrap_or_else(|_| PathBuf::from(resolved_path))],
+    }
+  }
+
+  /// 解析 `content` 中的所有文档并展开
+  pub fn resolve_documents(
+    &mut self,
+    resolved_path: &str,
+    content: &str,
+  ) -> Result<Vec<Value>, CompilationError> {
+    serde_yaml::Deserializer::from_str(content)
+      .map(|document| {
+        let value = Value::deserialize(document)
+          .map_err(|e| yaml_error_to_compilation_error(resolved_path, content, &e))?;
+        self.resolve(resolved_path, value)
+      })
+      .collect()
+  }
+
+  /// 展开 `resolved_path` 中的一个文档，`!include` 的路径相对于该文件
+  fn resolve(&mut self, resolved_path: &str, value: Value) -> Result<Value, CompilationError> {
+    let mut value = self.expand(resolved_path, value)?;
+    value
+      .apply_merge()
+      .map_err(|e| parse_error(resolved_path, format!("invalid `<<` merge key: {e}")))?;
+    Ok(value)
+  }
+
+  fn expand(&mut self, resolved_path: &str, value: Value) -> Result<Value, CompilationError> {
+    Ok(match value {
+      Value::Tagged(tagged) if tagged.tag == "include" => match tagged.value {
+        Value::String(path) => self.include(resolved_path, &path)?,
+        _ => {
+          return Err(parse_error(
+            resolved_path,
+            "`!include` expects the path of a YAML file".to_string(),
+          ))
+        }
+      },
+      // 其他自定义标签保留其值
+      Value::Tagged(tagged) => self.expand(resolved_path, tagged.value)?,
+      Value::Sequence(sequence) => Value::Sequence(
+        sequence
+          .into_iter()
+          .map(|item| self.expand(resolved_path, item))
+          .collect::<Result<_, _>>()?,
+      ),
+      Value::Mapping(mapping) => Value::Mapping(
+        mapping
+          .into_iter()
+          .map(|(key, value)| Ok((key, self.expand(resolved_path, value)?)))
+          .collect::<Result<_, CompilationError>>()?,
+      ),
+      Value::String(s) if self.interpolate_env => interpolate_env(&s),
+      value => value,
+    })
+  }
+
+  fn include(&mut self, importer: &str, path: &str) -> Result<Value, CompilationError> {
+    let joined = Path::new(importer)
+      .parent()
+      .unwrap_or(Path::new(""))
+      .join(path);
+    // 规范化路径，`a.yaml` 经 `../dir/a.yaml` 引入自身时也能检测到循环
+    let read_error = |e: std::io::Error| {
+      parse_error(
+        importer,
+        format!(
+          "failed to read `!include {path}` ({}): {e}",
+          joined.display()
+        ),
+      )
+    };
+    let included = canonicalize(&joined).map_err(read_error)?;
+    if self.stack.contains(&included) {
+      return Err(parse_error(
+        importer,
+        format!("circular `!include` of {}", included.display()),
+      ));
+    }
+    let included_path = included.to_string_lossy().to_string();
+    let content = read_to_string(&included).map_err(read_error)?;
+    if !self.included.contains(&included) {
+      self.included.push(included.clone());
+    }
+
+    self.stack.push(included);
+    let mut documents = serde_yaml::Deserializer::from_str(&content)
+      .map(|document| {
+        let value = Value::deserialize(document)
+          .map_err(|e| yaml_error_to_compilation_error(&included_path, &content, &e))?;
+        self.expand(&included_path, value)
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    self.stack.pop();
+
+    if documents.len() > 1 {
+      return Err(parse_error(
+        importer,
+        format!("`!include {path}` holds several documents, only one can be included"),
+      ));
+    }
+    Ok(documents.pop().unwrap_or(Value::Null))
+  }
+}
+
+fn parse_error(resolved_path: &str, reason: String) -> CompilationError {
+  CompilationError::ParseError {
+    resolved_path: resolved_path.to_string(),
+    msg: format!("Failed to parse {resolved_path}: {reason}"),
+  }
+}
+
+/// 替换 `${VAR}` 与 `${VAR:-default}`，未设置且没有默认值的变量替换为空字符串。
+/// 整个值只有一个占位符时，替换结果按普通 YAML 标量推断类型，如 `${PORT:-3000}`
+fn interpolate_env(value: &str) -> Value {
+  if !ENV_PLACEHOLDER.is_match(value) {
+    return Value::String(value.to_string());
+  }
+  let replaced = ENV_PLACEHOLDER
+    .replace_all(value, |captures: &Captures| {
+      std::env::var(&captures[1])
+        .ok()
+        .or_else(|| captures.get(2).map(|default| default.as_str().to_string()))
+        .unwrap_or_default()
+    })
+    .to_string();
+
+  let is_single_placeholder = ENV_PLACEHOLDER
+    .find(value)
+    .is_some_and(|m| m.start() == 0 && m.end() == value.len());
+  if is_single_placeholder {
+    if let Ok(scalar @ (Value::Null | Value::Bool(_) | Value::Number(_))) =
+      serde_yaml::from_str::<Value>(&replaced)
+    {
+      // 空字符串按 YAML 解析为 null，保留为空字符串
+      if !replaced.is_empty() {
+        return scalar;
+      }
+    }
+  }
+  Value::String(replaced)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+
+  #[test]
+  fn test_include_and_merge() {
+    let dir = std::env::temp_dir().join("farmfe_plugin_yaml_resolve");
+    fs::create_dir_all(dir.join("shared")).unwrap();
+    fs::write(
+      dir.join("shared/base.yaml"),
+      "port: 9000\nhosts: !include hosts.yaml\n",
+    )
+    .unwrap();
+    fs::write(dir.join("shared/hosts.yaml"), "- localhost\n").unwrap();
+    let entry = dir.join("config.yaml").to_string_lossy().to_string();
+
+    let content = "base: &base\n  mode: dev\nserver: !include shared/base.yaml\nprod:\n  <<: *base\n  debug: false\n";
+    let mut resolver = Resolver::new(&entry, false);
+    let documents = resolver.resolve_documents(&entry, content).unwrap();
+    assert_eq!(
+      documents[0],
+      serde_yaml::from_str::<Value>(
+        "base: { mode: dev }\nserver: { port: 9000, hosts: [localhost] }\nprod: { debug: false, mode: dev }\n"
+      )
+      .unwrap()
+    );
+    assert_eq!(
+      resolver.included,
+      vec![
+        fs::canonicalize(dir.join("shared/base.yaml")).unwrap(),
+        fs::canonicalize(dir.join("shared/hosts.yaml")).unwrap()
+      ]
+    );
+
+    fs::write(dir.join("loop.yaml"), "self: !include loop.yaml\n").unwrap();
+    let looped = dir.join("loop.yaml").to_string_lossy().to_string();
+    let err = Resolver::new(&looped, false)
+      .resolve_documents(&looped, "self: !include loop.yaml\n")
+      .unwrap_err();
+    assert!(matches!(err, CompilationError::ParseError { msg, .. } if msg.contains("circular")));
+
+    // `../` 路径指向同一文件
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    fs::write(
+      dir.join("nested/a.yaml"),
+      "next: !include ../nested/b.yaml
+",
+    )
+    .unwrap();
+    fs::write(
+      dir.join("nested/b.yaml"),
+      "back: !include ../nested/a.yaml
+",
+    )
+    .unwrap();
+    let nested = dir.join("nested/a.yaml").to_string_lossy().to_string();
+    let err = Resolver::new(&nested, false)
+      .resolve_documents(
+        &nested,
+        "next: !include ../nested/b.yaml
+",
+      )
+      .unwrap_err();
+    assert!(matches!(err, CompilationError::ParseError { msg, .. } if msg.contains("circular")));
+  }
+
+  #[test]
+  fn test_interpolate_env() {
+    std::env::set_var("FARMFE_PLUGIN_YAML_HOST", "example.com");
+    assert_eq!(
+      interpolate_env("https://${FARMFE_PLUGIN_YAML_HOST}/${FARMFE_PLUGIN_YAML_PATH:-api}"),
+      Value::String("https://example.com/api".to_string())
+    );
+    assert_eq!(
+      interpolate_env("${FARMFE_PLUGIN_YAML_PORT:-3000}"),
+      Value::Number(3000.into())
+    );
+    assert_eq!(
+      interpolate_env("${FARMFE_PLUGIN_YAML_UNSET}"),
+      Value::String(String::new())
+    );
+  }
+}
@@ -0,0 +1,158 @@
+use std::{fs::read_to_string, path::Path};
+
+use farmfe_core::{
+  error::CompilationError,
+  serde_json::{self, Value},
+};
+use globset::{Glob, GlobMatcher};
+use jsonschema::{ValidationError, Validator};
+use serde::Deserialize;
+
+/// `schemas` 配置项：匹配 `files` 的 YAML 文件使用 `schema` 校验
+#[derive(Debug, Deserialize)]
+pub struct SchemaOption {
+  /// 相对于项目根目录的 glob
+  pub files: String,
+  /// JSON Schema 文件路径，相对于项目根目录
+  pub schema: String,
+}
+
+/// 编译后的 schema 与匹配的文件
+pub struct Schema {
+  matcher: GlobMatcher,
+  validator: Validator,
+}
+
+impl Schema {
+  pub fn new(option: &SchemaOption, root: &str) -> Result<Self, String> {
+    let matcher = Glob::new(&option.files)
+      .map_err(|e| format!("Invalid `schemas` glob `{}`: {e}", option.files))?
+      .compile_matcher();
+    let schema_path = Path::new(root).join(&option.schema);
+    let schema = read_to_string(&schema_path)
+      .map_err(|e| e.to_string())
+      .and_then(|content| serde_json::from_str::<Value>(&content).map_err(|e| e.to_string()))
+      .map_err(|e| {
+        format!(
+          "Failed to read the JSON Schema {}: {e}",
+          schema_path.display()
+        )
+      })?;
+    let validator = jsonschema::validator_for(&schema)
+      .map_err(|e| format!("Invalid JSON Schema {}: {e}", schema_path.display()))?;
+    Ok(Self { matcher, validator })
+  }
+
+  /// `relative_path` 为相对于项目根目录的路径
+  pub fn matches(&self, relative_path: &str) -> bool {
+    self.matcher.is_match(relative_path)
+  }
+
+  /// 返回所有不符合 schema 的位置，多文档模式下 `document` 为文档的序号
+  pub fn validate(&self, value: &Value, document: Option<usize>) -> Vec<String> {
+    self
+      .validator
+      .iter_errors(value)
+      .map(|error| describe_error(value, &error, document))
+      .collect()
+  }
+}
+
+/// `- server.port: "80" is not of type "integer" (keyword `type`)`
+fn describe_error(value: &Value, error: &ValidationError, document: Option<usize>) -> String {
+  let path = yaml_path(value, error.instance_path.as_str());
+  let path = match (document, path.is_empty()) {
+    (None, true) => "<root>".to_string(),
+    (None, false) => path,
+    (Some(index), true) => format!("<document {index}>"),
+    (Some(index), false) => format!("<document {index}> {path}"),
+  };
+  let keyword = error
+    .schema_path
+    .as_str()
+    .rsplit('/')
+    .next()
+    .unwrap_or_default();
+  format!("- {path}: {error} (keyword `{keyword}`)")
+}
+
+/// 将 JSON Pointer 转换为 YAML 路径，如 `/servers/0/port` -> `servers[0].port`
+pub fn yaml_path(value: &Value, pointer: &str) -> String {
+  let mut path = String::new();
+  let mut current = Some(value);
+  for segment in pointer.split('/').skip(1) {
+    let segment = segment.replace("~1", "/").replace("~0", "~");
+    match current {
+      Some(Value::Array(items)) => {
+        let index = segment.parse::<usize>().ok();
+        path.push_str(&format!("[{segment}]"));
+        current = index.and_then(|index| items.get(index));
+      }
+      _ => {
+        if !path.is_empty() {
+          path.push('.');
+        }
+        path.push_str(&segment);
+        current = current.and_then(|current| current.get(&segment));
+      }
+    }
+  }
+  path
+}
+
+/// 校验失败时的编译错误
+pub fn validation_error(
+  resolved_path: &str,
+  schema: &str,
+  violations: &[String],
+) -> CompilationError {
+  CompilationError::ParseError {
+    resolved_path: resolved_path.to_string(),
+    msg: format!(
+      "{resolved_path} does not match the JSON Schema {schema}:\n{}",
+      violations.join("\n")
+    ),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_validate() {
+    let dir = std::env::temp_dir().join("farmfe_plugin_yaml_schema");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+      dir.join("servers.json"),
+      r#"{
+        "type": "object",
+        "required": ["name"],
+        "properties": {
+          "servers": { "type": "array", "items": { "properties": { "port": { "type": "integer", "maximum": 65535 } } } }
+        }
+      }"#,
+    )
+    .unwrap();
+    let schema = Schema::new(
+      &SchemaOption {
+        files: "config/**/*.yaml".to_string(),
+        schema: "servers.json".to_string(),
+      },
+      &dir.to_string_lossy(),
+    )
+    .unwrap();
+    assert!(schema.matches("config/dev/servers.yaml"));
+    assert!(!schema.matches("data/servers.yaml"));
+
+    let value = serde_json::json!({ "servers": [{ "port": 80 }, { "port": 70000 }] });
+    assert_eq!(
+      schema.validate(&value, None),
+      vec![
+        "- <root>: \"name\" is a required property (keyword `required`)".to_string(),
+        "- servers[1].port: 70000 is greater than the maximum of 65535 (keyword `maximum`)"
+          .to_string(),
+      ]
+    );
+  }
+}